@@ -6,16 +6,20 @@ import ControlToolbar from './components/ControlToolbar.jsx'
 function App() {
     const [city, setCity] = useState(null)
     const [agents, setAgents] = useState([])
+    const [vehicles, setVehicles] = useState([])
     const [metrics, setMetrics] = useState(null)
     const [isRunning, setIsRunning] = useState(false)
     const [socket, setSocket] = useState(null)
 
+    const [cityRevision, setCityRevision] = useState(null)
+
+    // refetched whenever the map was edited
     useEffect(() => {
         fetch('/api/city')
             .then(response => response.json())
             .then(data => setCity(data))
             .catch(error => console.error('Error fetching city data:', error))
-    }, [])
+    }, [cityRevision])
 
     let runSimulation = () => {
         fetch('/api/start')
@@ -81,7 +85,9 @@ function App() {
             try {
                 const data = JSON.parse(event.data)
                 setAgents(data.agents)
+                setVehicles(data.vehicles)
                 setMetrics(data.metrics)
+                setCityRevision(data.city_revision)
             } catch (error) {
                 console.error('Error parsing WebSocket message:', error)
             }
@@ -132,6 +138,7 @@ function App() {
             <CityMap
                 city={city}
                 agents={agents}
+                vehicles={vehicles}
                 metrics={metrics}
             />
         </>)}
@@ -1,11 +1,17 @@
 import React from 'react'
 
-const CityMap = ({city, agents = [], metrics}) => {
+const CityMap = ({city, agents = [], vehicles = [], metrics}) => {
     if (!city) return null
 
     agents = agents || [];
+    vehicles = vehicles || [];
 
-    const {width, height, cells} = city
+    const {width, height, cells, lines = []} = city
+    const stopLines = {}
+    lines.forEach(line => line.stops.forEach(({x, y}) => {
+        const key = `${x},${y}`
+        stopLines[key] = [...(stopLines[key] || []), line.name]
+    }))
     const congestionMap = metrics?.congestion_map || {}
 
     const getCellClass = (cellType) => {
@@ -58,12 +64,16 @@ const CityMap = ({city, agents = [], metrics}) => {
                             const cellAgents = agents.filter(
                                 agent => agent.position.x === x && agent.position.y === y
                             );
+                            const cellVehicles = vehicles.filter(
+                                vehicle => vehicle.position.x === x && vehicle.position.y === y
+                            );
+                            const stop = stopLines[`${x},${y}`];
 
                             return (
                                 <td
                                     key={`${x}-${y}`}
                                     className={`cell ${getCellClass(cell.cell_type)} ${getCongestionClass(x, y)}`}
-                                    title={`(${x}, ${y}) - ${cell.cell_type}`}
+                                    title={`(${x}, ${y}) - ${cell.cell_type}${cell.name ? ` (${cell.name})` : ''}${stop ? ` - stop ${stop.join(', ')}` : ''}`}
                                     style={{
                                         width: 30,
                                         height: 30,
@@ -73,7 +83,16 @@ const CityMap = ({city, agents = [], metrics}) => {
                                         position: 'relative',
                                     }}
                                 >
-                                    <span>{getCellDisplayChar(cell.cell_type)}</span>
+                                    <span>{stop ? '🚏' : cell.signal ? '🚦' : getCellDisplayChar(cell.cell_type)}</span>
+
+                                    {cellVehicles.map((vehicle, i) => (
+                                        <div
+                                            key={`vehicle-${i}`}
+                                            className="vehicle"
+                                            title={`Line ${vehicle.line} - ${vehicle.passengers} passengers`}
+                                            style={{position: 'absolute', bottom: 0, right: 0, fontSize: 12}}
+                                        >🚌</div>
+                                    ))}
 
                                     {cellAgents.map(agent => (
                                         <div
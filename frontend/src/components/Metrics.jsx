@@ -25,10 +25,14 @@ const Metrics = ({metrics}) => {
     const {
         timestamp,
         average_commute_time,
+        commute_times,
         average_distance,
         energy_usage,
         max_congestion,
         most_congested_position,
+        mode_share = {},
+        transit,
+        charging,
     } = metrics
 
     const congestionData = Object.entries(metrics.congestion_map || {})
@@ -88,8 +92,15 @@ const Metrics = ({metrics}) => {
                         </tr>
                         <tr>
                             <th>Average Commute Time</th>
-                            <td>{average_commute_time.toFixed(2)} seconds</td>
+                            <td>{average_commute_time.toFixed(2)} ticks per trip</td>
                         </tr>
+                        {commute_times && <tr>
+                            <th>Commute Times</th>
+                            <td>
+                                median {commute_times.median}, p90 {commute_times.p90},
+                                {` p99 ${commute_times.p99}`} ticks over {commute_times.trips} trips
+                            </td>
+                        </tr>}
                         <tr>
                             <th>Average Distance</th>
                             <td>{average_distance.toFixed(2)} cells</td>
@@ -98,6 +109,30 @@ const Metrics = ({metrics}) => {
                             <th>Energy Usage</th>
                             <td>{energy_usage.toFixed(2)} units</td>
                         </tr>
+                        <tr>
+                            <th>Mode Share</th>
+                            <td>
+                                {Object.entries(mode_share)
+                                    .map(([mode, share]) => `${mode} ${(share * 100).toFixed(0)}%`)
+                                    .join(', ')}
+                            </td>
+                        </tr>
+                        {transit && <tr>
+                            <th>Transit</th>
+                            <td>
+                                {transit.riders} riding, {transit.waiting} waiting,
+                                {` ${transit.boardings}`} boardings ({transit.transfers} transfers),
+                                {` ${transit.average_wait_time.toFixed(1)}`} ticks average wait
+                            </td>
+                        </tr>}
+                        {charging && <tr>
+                            <th>Charging</th>
+                            <td>
+                                {charging.charging} charging, {charging.queued} queued
+                                {` (${charging.demand} in need)`}, {charging.sessions} sessions,
+                                {` ${charging.average_queue_time.toFixed(1)}`} ticks average queue
+                            </td>
+                        </tr>}
                         <tr>
                             <th>Max Congestion</th>
                             <td>
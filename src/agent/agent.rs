@@ -1,52 +1,124 @@
+use crate::agent::mode::TransportMode;
+use crate::agent::schedule::{Activity, Destination};
 use crate::agent::state::AgentState;
-use crate::city::cell::{CellType, Position};
+use crate::agent::trip::Trip;
+use crate::city::cell::{CellType, Direction, Position};
 use crate::city::grid::CityGrid;
+use crate::routing::passability::PassabilityRules;
+use crate::routing::planner::RoutePlanner;
+use crate::simulation::clock::SimClock;
+use crate::simulation::config::{BatteryConfig, TransportConfig};
 use crate::simulation::simulation::WorldTime;
+use crate::simulation::transit::{Leg, Transit};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
+use std::collections::VecDeque;
+
+/// handle of an agent, its index in `Simulation::agents`
+pub type AgentId = u32;
+
+/// what the current path was planned for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedRoute {
+    pub goal: Position,
+    pub from: Position,
+    pub revision: u64, // city grid revision at planning time
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Agent {
-    pub id: String,
+    pub id: AgentId,
+    pub archetype: usize, // index into `SimulationConfig::archetypes`
     pub position: Position,
     pub home: Position,
-    pub work: Position,
+    pub work: Position, // home for archetypes that never work
     pub park: Position,
     pub state: AgentState,
-    pub work_counter: u8,
-    pub park_visits_remaining: u8,
-    pub path: Vec<Position>, // current_position -> goal
-    pub speed: f64,          // cells per second
-    pub last_move: Option<i64>,
+    /// daily activities, repeated after the last one
+    pub schedule: Vec<Activity>,
+    pub activity: usize,            // index of the current activity in `schedule`
+    pub path: VecDeque<Position>,   // current_position -> goal
+    pub heading: Option<Direction>, // direction the current cell was entered with
+    pub route: Option<PlannedRoute>,
+    /// modes the agent can use, slowest first, always includes walking
+    pub modes: Vec<TransportMode>,
+    pub mode: TransportMode, // of the current or last trip
+    pub speed: f64,          // cells per tick of the current mode
+    pub progress: f64,       // travel time in ticks not yet spent on a move
+    pub trips_by_mode: [usize; TransportMode::COUNT],
+    pub energy_used: f64,
+    pub battery: f64,                      // cells of driving range left
+    pub station: Option<Position>,         // energy station to charge at before going on
+    pub stranded_since: Option<WorldTime>, // stopped with an empty battery
+    /// rides left on a transit trip, the first one is boarded or waited for
+    pub itinerary: VecDeque<Leg>,
+    pub riding: Option<usize>, // index of the transit vehicle on board
+    pub transit_wait_since: Option<WorldTime>,
+    pub waiting_since: Option<WorldTime>, // queued at a red light or a full cell
+    pub blocked_since: Option<WorldTime>, // queued at a full cell
+    pub total_wait_time: WorldTime,
     pub total_distance: usize,
-    pub total_commute_time: WorldTime,
-    pub commute_start: Option<i64>,
+    pub reroutes: usize,
+    pub total_commute_time: WorldTime, // of completed trips to or from work
+    pub dwell_until: Option<WorldTime>, // stay at the current activity until this tick
+    pub trip: Option<Trip>,            // under way
+    /// completed trips, oldest first, capped at `SimulationConfig::trip_history`
+    pub trips: VecDeque<Trip>,
 }
 
 impl Agent {
-    pub fn new(id: String, home: Position, work: Position, park: Position) -> Self {
+    /// starts at home and begins the first activity of `schedule` on the first tick,
+    /// with an empty battery
+    pub fn new(
+        id: AgentId,
+        archetype: usize,
+        home: Position,
+        work: Position,
+        park: Position,
+        schedule: Vec<Activity>,
+        modes: Vec<TransportMode>,
+    ) -> Self {
         Self {
             id,
+            archetype,
             position: home,
             home,
             work,
             park,
             state: AgentState::AtHome,
-            work_counter: 0,
-            park_visits_remaining: 0,
-            path: Vec::new(),
+            activity: schedule.len().saturating_sub(1),
+            schedule,
+            path: VecDeque::new(),
+            heading: None,
+            route: None,
+            modes,
+            mode: TransportMode::Walk,
             speed: 1.0,
-            last_move: None,
+            progress: 0.0,
+            trips_by_mode: [0; TransportMode::COUNT],
+            energy_used: 0.0,
+            battery: 0.0,
+            station: None,
+            stranded_since: None,
+            itinerary: VecDeque::new(),
+            riding: None,
+            transit_wait_since: None,
+            waiting_since: None,
+            blocked_since: None,
+            total_wait_time: 0,
             total_distance: 0,
+            reroutes: 0,
             total_commute_time: 0,
-            commute_start: None,
+            dwell_until: None,
+            trip: None,
+            trips: VecDeque::new(),
         }
     }
 
     pub fn get_goal(&self) -> Position {
         match self.state {
             AgentState::GoingToWork | AgentState::Wandering => self.work,
-            AgentState::GoingHome | AgentState::AtWork => self.home,
+            AgentState::AtWork => self.work,
+            AgentState::GoingHome => self.home,
             AgentState::GoingToPark => self.park,
             AgentState::AtPark => self.park,
             AgentState::AtHome => self.home,
@@ -57,38 +129,53 @@ impl Agent {
         self.position == self.get_goal()
     }
 
-    pub fn update_state(&mut self, now: WorldTime) {
+    /// where the agent travels to next: the energy station on a charging detour,
+    /// the stop to board at on a transit trip, the goal otherwise
+    pub fn target(&self) -> Position {
+        if let Some(station) = self.station {
+            return station;
+        }
+        match self.itinerary.front() {
+            Some(leg) if self.riding.is_none() => leg.board,
+            _ => self.get_goal(),
+        }
+    }
+
+    fn is_dwelling(&self, now: WorldTime) -> bool {
+        self.dwell_until.is_some_and(|until| now < until)
+    }
+
+    fn destination(&self) -> Destination {
+        self.schedule
+            .get(self.activity)
+            .map_or(Destination::Home, |a| a.destination)
+    }
+
+    pub fn update_state(
+        &mut self,
+        now: WorldTime,
+        clock: &SimClock,
+        city: &CityGrid,
+        transport: &TransportConfig,
+        transit: &Transit,
+    ) {
         match self.state {
-            AgentState::AtHome => {
-                self.work_counter = (self.work_counter + 1) % 5;
-                if self.work_counter == 0 {
-                    self.park_visits_remaining = 2;
-                    self.state = AgentState::GoingToPark;
-                } else {
-                    self.state = AgentState::GoingToWork;
+            AgentState::AtHome | AgentState::AtWork | AgentState::AtPark => {
+                if self.is_dwelling(now) {
+                    return;
                 }
-                self.commute_start = Some(now);
-            }
-            AgentState::GoingToWork => {
+                let leaving_work = self.state == AgentState::AtWork;
+                self.activity = (self.activity + 1) % self.schedule.len().max(1);
+                self.state = AgentState::going_to(self.destination());
                 if self.has_reached_goal() {
-                    self.state = AgentState::AtWork;
-                    if let Some(start) = self.commute_start {
-                        self.total_commute_time += now;
-                        self.commute_start = None;
-                    }
+                    self.arrive(now, clock);
+                } else {
+                    self.depart(now, city, transport, transit, leaving_work);
                 }
             }
-            AgentState::AtWork => {
-                self.state = AgentState::GoingHome;
-                self.commute_start = Some(now);
-            }
-            AgentState::GoingHome => {
+            AgentState::GoingToWork | AgentState::GoingHome | AgentState::GoingToPark => {
                 if self.has_reached_goal() {
-                    self.state = AgentState::AtHome;
-                    if let Some(start) = self.commute_start {
-                        self.total_commute_time += now - start;
-                        self.commute_start = None;
-                    }
+                    self.arrive(now, clock);
                 }
             }
             AgentState::Wandering => {
@@ -96,178 +183,384 @@ impl Agent {
                     self.state = AgentState::AtWork;
                 }
             }
-            AgentState::GoingToPark => {
-                if self.has_reached_goal() {
-                    self.state = AgentState::AtPark;
-                    self.commute_start = Some(now);
-                }
+        }
+    }
+
+    /// start a trip to the current activity in the mode chosen for its distance,
+    /// or by transit if that is estimated to be quicker or no own mode covers the distance,
+    /// drivers on a low battery detour to the nearest energy station first
+    fn depart(
+        &mut self,
+        now: WorldTime,
+        city: &CityGrid,
+        transport: &TransportConfig,
+        transit: &Transit,
+        leaving_work: bool,
+    ) {
+        let goal = self.get_goal();
+        let distance = self.position.distance(&goal);
+        self.mode = transport.choose(&self.modes, distance);
+        let personal = transport.mode(self.mode);
+        let estimate = (distance as f64 / personal.speed).ceil() as WorldTime;
+        let too_far = personal.max_distance.is_some_and(|max| distance > max);
+        self.itinerary.clear();
+        if let Some((legs, duration)) = transit.plan(
+            &self.position,
+            &goal,
+            transport.transit.max_walk,
+            transport.walk.speed,
+        ) {
+            if too_far || duration < estimate {
+                self.mode = TransportMode::Transit;
+                self.itinerary = legs.into();
             }
-            AgentState::AtPark => {
-                if self.park_visits_remaining > 0 {
-                    self.park_visits_remaining -= 1;
-                    self.state = AgentState::GoingToPark;
-                } else {
-                    self.state = AgentState::GoingToWork;
-                }
-                self.commute_start = Some(now);
+        }
+        if self.mode == TransportMode::Drive && transport.battery.is_low(self.battery) {
+            self.station =
+                city.find_nearest_cell_with_type(&self.position, CellType::EnergyStation);
+        }
+        self.speed = transport.mode(self.mode).speed;
+        self.trips_by_mode[self.mode as usize] += 1;
+        let mut trip = Trip::new(self.position, goal, self.destination(), self.mode, now);
+        trip.commute = leaving_work || trip.purpose == Destination::Work;
+        self.trip = Some(trip);
+    }
+
+    /// start the current activity and complete the trip to it
+    fn arrive(&mut self, now: WorldTime, clock: &SimClock) {
+        let destination = self.destination();
+        self.state = AgentState::at(destination);
+        self.itinerary.clear();
+        self.station = None;
+        let departed = self.trip.as_ref().map_or(now, |trip| trip.departure);
+        self.dwell_until = self
+            .schedule
+            .get(self.activity)
+            .map(|a| a.leave_at(departed, now, clock));
+        if let Some(mut trip) = self.trip.take() {
+            trip.arrival = Some(now);
+            if trip.commute {
+                self.total_commute_time += now - trip.departure;
             }
+            self.trips.push_back(trip);
         }
     }
 
-    pub fn move_along_path(&mut self, now: WorldTime, city: &CityGrid) -> bool {
-        if self.path.is_empty() {
-            return false;
+    /// count `ticks` of waiting towards the trip under way
+    pub fn add_wait(&mut self, ticks: WorldTime) {
+        if let Some(trip) = &mut self.trip {
+            trip.wait_time += ticks;
         }
+    }
 
-        let next_pos = self.path[0];
+    /// move as far as the speed allows this tick, one cell per `1 / speed` ticks
+    /// with unused time carried over, returns the number of cells moved
+    pub fn move_along_path(
+        &mut self,
+        now: WorldTime,
+        city: &CityGrid,
+        rules: &PassabilityRules,
+    ) -> usize {
+        if self.path.is_empty() {
+            self.progress = 0.0;
+            return 0;
+        }
+        self.progress += 1.0;
 
-        if let Some(cell) = city.get_cell(&next_pos) {
-            // do not move into empty blocks
-            // the path finding algorithm should already have guaranteed that
-            // if not, this is a bug, print an error log
-            if cell.cell_type == CellType::Empty {
-                log::error!("agent {}: moving to empty block at {:?}", self.id, next_pos);
+        let mut moved = 0;
+        while let Some(&next_pos) = self.path.front() {
+            let Some(cell) = city.get_cell(&next_pos) else {
+                break;
+            };
+            // through-traffic stays on through cells, buildings are only entered at the goal,
+            // the route is stale if the cell changed since it was planned
+            if !rules.allows(city, &self.position, cell, &self.target()) {
+                log::warn!(
+                    "agent {}: cannot pass {:?} at {:?}, re-planning",
+                    self.id,
+                    cell.cell_type,
+                    next_pos
+                );
                 self.path.clear();
-                return false;
+                self.route = None;
+                break;
+            }
+            // one-way roads and turn restrictions, the route is stale if violated
+            let Some(direction) = Direction::between(&self.position, &next_pos)
+                .filter(|d| city.can_move(&self.position, *d, self.heading))
+            else {
+                log::warn!(
+                    "agent {}: illegal move to {:?}, re-planning",
+                    self.id,
+                    next_pos
+                );
+                self.path.clear();
+                self.route = None;
+                break;
+            };
+            // wait in place at a red light or until someone leaves the next cell,
+            // a stopped agent starts from zero
+            let red = !cell.is_green(now, direction);
+            if red || cell.is_full() {
+                self.waiting_since.get_or_insert(now);
+                if red {
+                    self.blocked_since = None;
+                } else {
+                    self.blocked_since.get_or_insert(now);
+                }
+                self.total_wait_time += 1;
+                self.add_wait(1);
+                self.progress = 0.0;
+                return moved;
             }
-        }
 
-        let should_move = match self.last_move {
-            Some(last) => {
-                let elapsed = now - last;
-                elapsed as f64 >= 1.0 / self.speed
+            let speed = cell.limit_speed(self.speed);
+            if speed <= 0.0 || self.progress < 1.0 / speed {
+                break;
             }
-            None => true,
-        };
+            self.progress -= 1.0 / speed;
 
-        if should_move {
-            let next_pos = self.path[0];
-            log::debug!(
-                "agent {}: attempting move from {:?} to {:?}",
-                self.id,
-                self.position,
-                next_pos
-            );
+            self.step(direction);
+            moved += 1;
+        }
+        if self.path.is_empty() {
+            self.progress = 0.0;
+        }
+        moved
+    }
 
-            self.total_distance += self.position.distance(&next_pos);
-            self.position = next_pos;
-            self.path.remove(0);
-            self.last_move = Some(now);
-            log::debug!("agent {}: moved to {:?}", self.id, self.position);
+    /// move to the next cell of the path, the caller moves the occupancy
+    pub fn step(&mut self, direction: Direction) {
+        let Some(next_pos) = self.path.pop_front() else {
+            return;
+        };
+        log::debug!(
+            "agent {}: moving from {:?} to {:?}",
+            self.id,
+            self.position,
+            next_pos
+        );
+        self.total_distance += self.position.distance(&next_pos);
+        self.heading = Some(direction);
+        self.position = next_pos;
+        if let Some(trip) = &mut self.trip {
+            trip.visit(next_pos);
         }
-        should_move
+        self.waiting_since = None;
+        self.blocked_since = None;
     }
 
-    /// find the path to goal using BFS
-    pub fn find_goal_path(&mut self, city: &CityGrid) {
-        let goal = self.get_goal();
+    /// whether the agent has queued in front of a full cell for `timeout` ticks, never if 0
+    pub fn is_gridlocked(&self, now: WorldTime, timeout: WorldTime) -> bool {
+        timeout > 0
+            && self
+                .blocked_since
+                .is_some_and(|since| now - since >= timeout)
+    }
+
+    /// put the agent on `position` and drop its route and remaining rides,
+    /// the caller moves the occupancy
+    pub fn relocate(&mut self, position: Position) {
+        self.position = position;
+        if let Some(trip) = &mut self.trip {
+            trip.visit(position);
+        }
         self.path.clear();
-        if self.has_reached_goal() {
+        self.route = None;
+        self.heading = None;
+        self.waiting_since = None;
+        self.blocked_since = None;
+        self.progress = 0.0;
+        self.itinerary.clear();
+        self.transit_wait_since = None;
+        self.station = None;
+    }
+
+    /// drivers whose remaining path is longer than their range detour to the
+    /// nearest energy station, checked while driving. no detour is taken when a
+    /// full charge there would not cover the way back and the rest of the path either
+    pub fn check_range(
+        &mut self,
+        city: &CityGrid,
+        planner: &RoutePlanner,
+        battery: &BatteryConfig,
+    ) {
+        if self.mode != TransportMode::Drive
+            || !battery.enabled()
+            || self.station.is_some()
+            || self.path.len() as f64 <= self.battery
+        {
             return;
         }
+        let remaining = self.path.len();
+        self.station = city
+            .find_nearest_cell_with_type(&self.position, CellType::EnergyStation)
+            .filter(|station| {
+                let detour = self.position.distance(station);
+                detour > 0 && (detour + remaining) as f64 <= battery.range
+            });
+        if self.station.is_some() {
+            log::info!(
+                "agent {}: {} cells left to go on {:.0} cells of range, detouring to charge",
+                self.id,
+                self.path.len(),
+                self.battery
+            );
+            self.find_goal_path(city, planner);
+        }
+    }
 
-        let mut queue = VecDeque::new();
-        let mut visited = HashMap::new();
+    /// stay put with an empty battery for a tick, the caller charges at the roadside
+    pub fn stop_empty(&mut self, now: WorldTime) {
+        if self.stranded_since.is_none() {
+            log::warn!(
+                "agent {}: battery empty at ({}, {}), waiting for roadside charging",
+                self.id,
+                self.position.x,
+                self.position.y
+            );
+        }
+        self.stranded_since.get_or_insert(now);
+        self.total_wait_time += 1;
+        self.add_wait(1);
+        self.progress = 0.0;
+    }
 
-        queue.push_back(self.position);
-        visited.insert(self.position, None);
+    /// get on transit vehicle `vehicle` after waiting `waited` ticks at the stop,
+    /// the caller moves the occupancy
+    pub fn board(&mut self, vehicle: usize, waited: WorldTime) {
+        self.riding = Some(vehicle);
+        self.add_wait(waited);
+        self.transit_wait_since = None;
+        self.path.clear();
+        self.route = None;
+        self.waiting_since = None;
+        self.blocked_since = None;
+    }
 
-        const MAX_DEPTH: usize = 1024; // BFS max search depth
-        let mut depth = 0;
-        let mut found = false;
-        while !queue.is_empty() && !found && depth < MAX_DEPTH {
-            depth += 1;
-            let current = queue.pop_front().unwrap();
+    /// move along with the transit vehicle on board
+    pub fn ride_to(&mut self, position: Position) {
+        self.position = position;
+        if let Some(trip) = &mut self.trip {
+            trip.visit(position);
+        }
+    }
 
-            found = current == goal;
-            if found {
-                break;
-            }
+    /// get off at the current position, returns whether another ride follows,
+    /// the caller moves the occupancy
+    pub fn alight(&mut self) -> bool {
+        self.riding = None;
+        self.itinerary.pop_front();
+        self.heading = None;
+        !self.itinerary.is_empty()
+    }
 
-            const MOVE_DIRECTIONS: [(i32, i32); 4] = [
-                (0, 1),  // down
-                (1, 0),  // right
-                (0, -1), // up
-                (-1, 0), // left
-            ];
-            for (x, y) in MOVE_DIRECTIONS {
-                let next_pos = Position {
-                    x: if x < 0 && current.x == 0 {
-                        continue;
-                    } else if x > 0 {
-                        current.x + (x as usize)
-                    } else if x < 0 {
-                        current.x - (-x as usize)
-                    } else {
-                        current.x
-                    },
-                    y: if y < 0 && current.y == 0 {
-                        continue;
-                    } else if y > 0 {
-                        current.y + (y as usize)
-                    } else if y < 0 {
-                        current.y - (-y as usize)
-                    } else {
-                        current.y
-                    },
-                };
-                if visited.contains_key(&next_pos) {
-                    continue;
-                }
-                match city.get_cell(&next_pos) {
-                    Some(cell) => match cell.cell_type {
-                        CellType::Empty => continue,
-                        _ => {
-                            // possible, try visit
-                            queue.push_back(next_pos);
-                            visited.insert(next_pos, Some(current));
-                        }
-                    },
-                    None => continue,
-                }
+    /// whether the current path no longer leads to the goal
+    pub fn needs_replan(&self, city: &CityGrid) -> bool {
+        let goal = self.target();
+        match &self.route {
+            None => self.position != goal,
+            Some(route) if route.goal != goal => true,
+            Some(route) if self.path.is_empty() => {
+                // arrived, or the last attempt failed: retry only if something changed
+                self.position != goal
+                    && (route.from != self.position || route.revision != city.revision())
             }
+            Some(route) => !city.is_route_valid(&self.path, route.revision),
         }
-        if found {
-            let mut current = goal;
-            let mut path = Vec::new();
-            while current != self.position {
-                path.push(current);
-                current = visited[&current].unwrap();
-            }
-            self.path = path.into_iter().rev().collect();
+    }
+
+    /// keep the current path, re-plan only if it is stale
+    pub fn update_path(&mut self, city: &CityGrid, planner: &RoutePlanner) {
+        if self.riding.is_none() && self.needs_replan(city) {
+            self.find_goal_path(city, planner);
+        }
+    }
+
+    /// switch to a cheaper route if the remaining path became much slower,
+    /// returns whether the route changed
+    pub fn reroute(&mut self, city: &CityGrid, planner: &RoutePlanner, threshold: f64) -> bool {
+        if self.path.is_empty() {
+            return false;
+        }
+        let path = self.path.make_contiguous();
+        let Some(current_cost) = planner.path_cost(city, &self.position, path, self.heading) else {
+            self.find_goal_path(city, planner);
+            return true;
+        };
+        let goal = self.target();
+        // only an alternative cheaper than this can win, so the search stops there
+        let max_cost = (current_cost as f64 / threshold).ceil() as u64;
+        let Some(alternative) =
+            planner.plan_within(city, self.position, goal, self.heading, max_cost)
+        else {
+            return false;
+        };
+        let Some(alternative_cost) =
+            planner.path_cost(city, &self.position, &alternative, self.heading)
+        else {
+            return false;
+        };
+        if current_cost as f64 > alternative_cost as f64 * threshold {
+            log::debug!(
+                "agent {}: rerouting, remaining cost {} vs {}",
+                self.id,
+                current_cost,
+                alternative_cost
+            );
+            self.path = alternative.into();
+            self.route = Some(PlannedRoute {
+                goal,
+                from: self.position,
+                revision: city.revision(),
+            });
+            self.reroutes += 1;
+            true
         } else {
-            let g = self.get_goal();
-            log::error!("agent {}: no path to goal ({},{})", self.id, g.x, g.y);
-            // fallback to a direct path to goal, regardless of buildings on map
-            if let Some(cell) = city.get_cell(&self.position) {
-                if cell.cell_type != CellType::Road {
-                    if let Some(nearest_road) =
-                        city.find_nearest_cell_with_type(&self.position, CellType::Road)
-                    {
-                        let mut current = self.position;
+            false
+        }
+    }
 
-                        // move along X axis
-                        while current.x != nearest_road.x {
-                            if current.x < nearest_road.x {
-                                current.x += 1;
-                            } else {
-                                current.x -= 1;
-                            }
-                            self.path.push(current);
-                        }
+    /// find the path to the target with the given planner
+    pub fn find_goal_path(&mut self, city: &CityGrid, planner: &RoutePlanner) {
+        let goal = self.target();
+        self.path.clear();
+        self.route = Some(PlannedRoute {
+            goal,
+            from: self.position,
+            revision: city.revision(),
+        });
+        if self.position == goal {
+            return;
+        }
 
-                        // move along Y axis
-                        while current.y != nearest_road.y {
-                            if current.y < nearest_road.y {
-                                current.y += 1;
-                            } else {
-                                current.y -= 1;
-                            }
-                            self.path.push(current);
-                        }
-                    }
-                }
+        if let Some(path) = planner.plan(city, self.position, goal, self.heading) {
+            self.path = path.into();
+        } else if let Some(station) = self.station.take() {
+            // the nearest station may be cut off, charge at the nearest one with a path to it
+            let mut stations = city.find_cells_of_type(CellType::EnergyStation);
+            stations.retain(|s| *s != station);
+            stations.sort_by_key(|s| self.position.distance(s));
+            self.station = stations.into_iter().find(|s| {
+                planner
+                    .plan(city, self.position, *s, self.heading)
+                    .is_some()
+            });
+            if self.station.is_none() {
+                log::warn!(
+                    "agent {}: no energy station reachable, skipping the charge",
+                    self.id
+                );
             }
+            self.find_goal_path(city, planner);
+        } else {
+            // wait in place, `needs_replan` retries once the city or the position changes
+            log::error!(
+                "agent {}: no path to goal ({},{}), waiting",
+                self.id,
+                goal.x,
+                goal.y
+            );
         }
     }
 }
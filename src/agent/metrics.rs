@@ -1,26 +1,37 @@
-use crate::agent::agent::Agent;
+use crate::agent::agent::{Agent, AgentId};
+use crate::agent::mode::TransportMode;
 use crate::agent::state::AgentState;
 use crate::city::cell::Position;
-use serde::{Deserialize, Serialize};
 use crate::simulation::simulation::WorldTime;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentMetrics {
-    pub id: String,
+    pub id: AgentId,
     pub state: AgentState,
     pub total_distance: usize,
+    pub reroutes: usize,
+    pub waiting: bool,
+    pub total_wait_time: WorldTime,
     pub total_commute_time: WorldTime,
     pub current_position: Position,
+    pub trips_by_mode: [usize; TransportMode::COUNT],
+    pub energy_used: f64,
 }
 
 impl From<&Agent> for AgentMetrics {
     fn from(agent: &Agent) -> Self {
         AgentMetrics {
-            id: agent.id.clone(),
+            id: agent.id,
             state: agent.state,
             total_distance: agent.total_distance,
+            reroutes: agent.reroutes,
+            waiting: agent.waiting_since.is_some(),
+            total_wait_time: agent.total_wait_time,
             total_commute_time: agent.total_commute_time,
             current_position: agent.position,
+            trips_by_mode: agent.trips_by_mode,
+            energy_used: agent.energy_used,
         }
     }
 }
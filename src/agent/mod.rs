@@ -1,3 +1,7 @@
+#[allow(clippy::module_inception)]
 pub mod agent;
 pub mod metrics;
+pub mod mode;
+pub mod schedule;
 pub mod state;
+pub mod trip;
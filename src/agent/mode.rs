@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+/// how an agent travels on a trip
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum TransportMode {
+    Walk,
+    Cycle,
+    Drive,
+    /// rides transit vehicles, walks to and from stops
+    Transit,
+}
+
+impl TransportMode {
+    pub const COUNT: usize = 4;
+
+    /// personal modes slowest first, the order mode choice tries them in, then transit
+    pub const ALL: [TransportMode; TransportMode::COUNT] = [
+        TransportMode::Walk,
+        TransportMode::Cycle,
+        TransportMode::Drive,
+        TransportMode::Transit,
+    ];
+}
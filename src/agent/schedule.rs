@@ -0,0 +1,43 @@
+use crate::simulation::clock::SimClock;
+use crate::simulation::simulation::WorldTime;
+use serde::{Deserialize, Serialize};
+
+/// where an activity takes place, one of the agent's anchors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Destination {
+    Home,
+    Work,
+    Park,
+}
+
+/// how long an agent stays once it arrived
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stay {
+    /// this many ticks
+    For(WorldTime),
+    /// until the clock next shows this tick of the day
+    Until(WorldTime),
+}
+
+/// one entry of an agent's daily schedule, resolved to ticks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Activity {
+    pub destination: Destination,
+    pub stay: Stay,
+}
+
+impl Activity {
+    /// tick at which an agent that set off at `departed` and arrived at `now` moves on
+    ///
+    /// `Until` ends the first time the clock shows its tick of the day after setting
+    /// off, an agent held up past it moves on right away instead of staying a day
+    pub fn leave_at(&self, departed: WorldTime, now: WorldTime, clock: &SimClock) -> WorldTime {
+        match self.stay {
+            Stay::For(ticks) => now + ticks,
+            Stay::Until(tick_of_day) => {
+                let deadline = departed + (tick_of_day - departed).rem_euclid(clock.day_ticks());
+                deadline.max(now)
+            }
+        }
+    }
+}
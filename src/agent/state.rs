@@ -1,3 +1,4 @@
+use crate::agent::schedule::Destination;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -12,6 +13,26 @@ pub enum AgentState {
     Wandering,
 }
 
+impl AgentState {
+    /// travelling to `destination`
+    pub fn going_to(destination: Destination) -> Self {
+        match destination {
+            Destination::Home => AgentState::GoingHome,
+            Destination::Work => AgentState::GoingToWork,
+            Destination::Park => AgentState::GoingToPark,
+        }
+    }
+
+    /// staying at `destination`
+    pub fn at(destination: Destination) -> Self {
+        match destination {
+            Destination::Home => AgentState::AtHome,
+            Destination::Work => AgentState::AtWork,
+            Destination::Park => AgentState::AtPark,
+        }
+    }
+}
+
 impl fmt::Display for AgentState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
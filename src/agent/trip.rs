@@ -0,0 +1,139 @@
+use crate::agent::agent::AgentId;
+use crate::agent::mode::TransportMode;
+use crate::agent::schedule::Destination;
+use crate::city::cell::Position;
+use crate::simulation::simulation::WorldTime;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// one trip from an activity to the next, recorded as the agent goes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trip {
+    pub origin: Position,
+    pub destination: Position,
+    pub purpose: Destination, // activity at the destination
+    pub commute: bool,        // to or from work
+    pub mode: TransportMode,
+    pub departure: WorldTime,
+    pub arrival: Option<WorldTime>, // `None` while under way
+    /// ticks queued at red lights and full cells, at transit stops and for a charging slot
+    pub wait_time: WorldTime,
+    /// cells passed in order, from the origin to the destination
+    pub route: Vec<Position>,
+}
+
+/// a trip with the agent that made it, as listed and exported in bulk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentTrip {
+    pub agent: AgentId,
+    #[serde(flatten)]
+    pub trip: Trip,
+}
+
+/// completed trips in arrival order for bulk listing, the oldest are dropped
+/// once `capacity` are kept
+///
+/// every trip logged gets a cursor counting from 0, pages continue from one
+#[derive(Debug, Clone, Default)]
+pub struct TripLog {
+    trips: VecDeque<AgentTrip>,
+    dropped: u64, // trips dropped so far, the cursor of the oldest kept one
+    capacity: usize,
+}
+
+/// a page of the trip log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TripPage {
+    pub trips: Vec<AgentTrip>,
+    /// cursor to continue from
+    pub next: u64,
+    /// whether more trips are logged after this page
+    pub more: bool,
+    /// trips from the requested cursor on that were dropped before they were listed
+    pub dropped: u64,
+}
+
+impl TripLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            trips: VecDeque::with_capacity(capacity.min(1024)),
+            dropped: 0,
+            capacity,
+        }
+    }
+
+    pub fn extend(&mut self, trips: &[AgentTrip]) {
+        for trip in trips {
+            if self.trips.len() == self.capacity {
+                self.dropped += 1;
+                if self.trips.pop_front().is_none() {
+                    continue; // nothing is kept
+                }
+            }
+            self.trips.push_back(trip.clone());
+        }
+    }
+
+    /// up to `limit` trips from `cursor` on that arrived after tick `since`
+    pub fn page(&self, cursor: u64, since: WorldTime, limit: usize) -> TripPage {
+        let offset = cursor
+            .saturating_sub(self.dropped)
+            .min(self.trips.len() as u64) as usize;
+        let arrived = self
+            .trips
+            .partition_point(|t| t.trip.arrival.is_none_or(|arrival| arrival <= since));
+        let start = offset.max(arrived);
+        let trips = self
+            .trips
+            .range(start..)
+            .take(limit)
+            .cloned()
+            .collect::<Vec<_>>();
+        let end = start + trips.len();
+        TripPage {
+            trips,
+            next: self.dropped + end as u64,
+            more: end < self.trips.len(),
+            dropped: self.dropped.saturating_sub(cursor),
+        }
+    }
+}
+
+impl Trip {
+    pub fn new(
+        origin: Position,
+        destination: Position,
+        purpose: Destination,
+        mode: TransportMode,
+        departure: WorldTime,
+    ) -> Self {
+        Self {
+            origin,
+            destination,
+            purpose,
+            commute: false,
+            mode,
+            departure,
+            arrival: None,
+            wait_time: 0,
+            route: vec![origin],
+        }
+    }
+
+    /// ticks from departure to arrival, `None` while under way
+    pub fn duration(&self) -> Option<WorldTime> {
+        self.arrival.map(|arrival| arrival - self.departure)
+    }
+
+    /// cells moved (Manhattan steps along the route)
+    pub fn distance(&self) -> usize {
+        self.route.windows(2).map(|w| w[0].distance(&w[1])).sum()
+    }
+
+    /// record a cell entered, staying put is not recorded
+    pub fn visit(&mut self, position: Position) {
+        if self.route.last() != Some(&position) {
+            self.route.push(position);
+        }
+    }
+}
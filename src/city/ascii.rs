@@ -0,0 +1,214 @@
+//! plain text city maps, using the same glyphs as `Display for CityGrid`
+//!
+//! ```text
+//! # comment
+//! capacities {"Road": 6}
+//! places {"House": 4}
+//! line {"name": "1", "stops": [{"x": 2, "y": 0}, {"x": 2, "y": 1}]}
+//! legend A {"cell_type": "Road", "lanes": 2, "name": "Main St"}
+//! RRARR
+//! H.R.O
+//! ```
+//!
+//! keywords are followed by a space, every other line is a map row, all rows have the same width;
+//! a legend maps an extra glyph to cell attributes, given as a `CellConfig` without `x` and `y`,
+//! each `line` adds a `TransitLine`
+
+use crate::city::cell::CellType;
+use crate::city::config::{cell_type_name, CellConfig, CityConfig, ConfigError};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+const COMMENT: char = '#';
+const LEGEND: &str = "legend";
+const CAPACITIES: &str = "capacities";
+const PLACES: &str = "places";
+const LINE: &str = "line";
+
+/// glyphs handed out to legend entries when writing
+const LEGEND_GLYPHS: &str = "ABCDFGIJKLMNQSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+impl CityConfig {
+    pub fn from_ascii(text: &str) -> Result<Self, ConfigError> {
+        let mut legend: HashMap<char, serde_json::Value> = HashMap::new();
+        let mut capacities = BTreeMap::new();
+        let mut places = BTreeMap::new();
+        let mut lines = Vec::new();
+        let mut rows: Vec<(usize, &str)> = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let trimmed = line.trim_end();
+            if trimmed.is_empty() || trimmed.starts_with(COMMENT) {
+                continue;
+            }
+            if let Some(rest) = keyword(trimmed, LEGEND) {
+                let rest = rest.trim_start();
+                let mut chars = rest.chars();
+                let glyph = chars
+                    .next()
+                    .ok_or_else(|| map_error(line_no, "legend without glyph".to_string()))?;
+                if CellType::from_glyph(glyph).is_some() {
+                    return Err(map_error(
+                        line_no,
+                        format!("legend glyph '{}' shadows a cell type", glyph),
+                    ));
+                }
+                let attributes: serde_json::Value = serde_json::from_str(chars.as_str())
+                    .map_err(|e| map_error(line_no, format!("invalid legend: {}", e)))?;
+                if !attributes.is_object() {
+                    return Err(map_error(
+                        line_no,
+                        "legend must be a JSON object".to_string(),
+                    ));
+                }
+                legend.insert(glyph, attributes);
+            } else if let Some(rest) = keyword(trimmed, CAPACITIES) {
+                capacities = serde_json::from_str(rest)
+                    .map_err(|e| map_error(line_no, format!("invalid capacities: {}", e)))?;
+            } else if let Some(rest) = keyword(trimmed, PLACES) {
+                places = serde_json::from_str(rest)
+                    .map_err(|e| map_error(line_no, format!("invalid places: {}", e)))?;
+            } else if let Some(rest) = keyword(trimmed, LINE) {
+                let transit_line = serde_json::from_str(rest)
+                    .map_err(|e| map_error(line_no, format!("invalid line: {}", e)))?;
+                lines.push(transit_line);
+            } else {
+                rows.push((line_no, trimmed));
+            }
+        }
+
+        let width = rows.first().map_or(0, |(_, row)| row.chars().count());
+        let mut cells = Vec::new();
+        for (y, &(line_no, row)) in rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(map_error(
+                    line_no,
+                    format!("row has {} cells, expected {}", row.chars().count(), width),
+                ));
+            }
+            for (x, glyph) in row.chars().enumerate() {
+                if let Some(cell_type) = CellType::from_glyph(glyph) {
+                    if cell_type != CellType::Empty {
+                        cells.push(CellConfig::new(x, y, cell_type_name(cell_type)));
+                    }
+                } else if let Some(attributes) = legend.get(&glyph) {
+                    let mut attributes = attributes.clone();
+                    attributes["x"] = x.into();
+                    attributes["y"] = y.into();
+                    let cell = serde_json::from_value(attributes).map_err(|e| {
+                        map_error(line_no, format!("legend '{}' is not a cell: {}", glyph, e))
+                    })?;
+                    cells.push(cell);
+                } else {
+                    return Err(map_error(line_no, format!("unknown glyph '{}'", glyph)));
+                }
+            }
+        }
+
+        Ok(Self {
+            width,
+            height: rows.len(),
+            cells,
+            capacities,
+            places,
+            lines,
+        })
+    }
+
+    /// the city as a text map, `Err` if a cell cannot be written without changing it
+    pub fn to_ascii(&self) -> Result<String, ConfigError> {
+        // later entries for the same position win, like in `to_city_grid`
+        let mut grid: Vec<Vec<Option<&CellConfig>>> = vec![vec![None; self.width]; self.height];
+        for cell in &self.cells {
+            if cell.x >= self.width || cell.y >= self.height {
+                return Err(ConfigError::Unrepresentable(format!(
+                    "cell ({}, {}) is outside the {}x{} map",
+                    cell.x, cell.y, self.width, self.height
+                )));
+            }
+            grid[cell.y][cell.x] = Some(cell);
+        }
+
+        let mut legend: Vec<(char, String)> = Vec::new();
+        let mut glyphs = LEGEND_GLYPHS.chars();
+        let mut rows = String::new();
+        for row in &grid {
+            for cell in row {
+                let glyph = match cell {
+                    None => CellType::Empty.glyph(),
+                    Some(cell) => {
+                        let cell_type = cell.cell_type.parse::<CellType>().map_err(|_| {
+                            ConfigError::Unrepresentable(format!(
+                                "cell ({}, {}) has unknown type {:?}",
+                                cell.x, cell.y, cell.cell_type
+                            ))
+                        })?;
+                        let attributes = legend_attributes(cell);
+                        if attributes == legend_attributes(&CellConfig::new(0, 0, &cell.cell_type))
+                        {
+                            cell_type.glyph()
+                        } else if let Some((glyph, _)) =
+                            legend.iter().find(|(_, a)| *a == attributes)
+                        {
+                            *glyph
+                        } else {
+                            let glyph = glyphs.next().ok_or_else(|| {
+                                ConfigError::Unrepresentable(format!(
+                                    "more than {} distinct cell attributes, cell ({}, {}) needs another legend glyph",
+                                    LEGEND_GLYPHS.len(),
+                                    cell.x,
+                                    cell.y
+                                ))
+                            })?;
+                            legend.push((glyph, attributes));
+                            glyph
+                        }
+                    }
+                };
+                rows.push(glyph);
+            }
+            rows.push('\n');
+        }
+
+        let mut out = String::new();
+        if !self.capacities.is_empty() {
+            let capacities = serde_json::to_string(&self.capacities).unwrap_or_default();
+            let _ = writeln!(out, "{} {}", CAPACITIES, capacities);
+        }
+        if !self.places.is_empty() {
+            let places = serde_json::to_string(&self.places).unwrap_or_default();
+            let _ = writeln!(out, "{} {}", PLACES, places);
+        }
+        for line in &self.lines {
+            let line = serde_json::to_string(line).unwrap_or_default();
+            let _ = writeln!(out, "{} {}", LINE, line);
+        }
+        for (glyph, attributes) in &legend {
+            let _ = writeln!(out, "{} {} {}", LEGEND, glyph, attributes);
+        }
+        out.push_str(&rows);
+        Ok(out)
+    }
+}
+
+/// the rest of a `keyword` line, rows of legend glyphs may spell a keyword but never
+/// continue with whitespace
+fn keyword<'a>(line: &'a str, keyword: &str) -> Option<&'a str> {
+    line.strip_prefix(keyword)
+        .filter(|rest| rest.starts_with(char::is_whitespace))
+}
+
+fn map_error(line: usize, message: String) -> ConfigError {
+    ConfigError::Map { line, message }
+}
+
+/// cell attributes as a JSON object without the position
+fn legend_attributes(cell: &CellConfig) -> String {
+    let mut value = serde_json::to_value(cell).unwrap_or_default();
+    if let Some(object) = value.as_object_mut() {
+        object.remove("x");
+        object.remove("y");
+    }
+    value.to_string()
+}
@@ -1,3 +1,5 @@
+use crate::city::signal::SignalPlan;
+use crate::simulation::simulation::WorldTime;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -11,20 +13,137 @@ pub enum CellType {
     Empty,
 }
 
+impl CellType {
+    pub const COUNT: usize = 6;
+
+    pub const ALL: [CellType; CellType::COUNT] = [
+        CellType::Road,
+        CellType::House,
+        CellType::Office,
+        CellType::Park,
+        CellType::EnergyStation,
+        CellType::Empty,
+    ];
+
+    /// how many agents fit on a cell of this type unless configured otherwise,
+    /// `None` is unlimited
+    pub fn default_capacity(&self) -> Option<usize> {
+        match self {
+            CellType::Road => Some(4),
+            _ => None,
+        }
+    }
+
+    /// residents of a house, jobs at an office or charging slots at an energy
+    /// station unless configured otherwise, `None` is unlimited
+    pub fn default_places(&self) -> Option<usize> {
+        match self {
+            CellType::House => Some(8),
+            CellType::Office => Some(40),
+            CellType::EnergyStation => Some(2),
+            _ => None,
+        }
+    }
+
+    /// map character, as used by `Display`
+    pub fn glyph(&self) -> char {
+        match self {
+            CellType::Road => 'R',
+            CellType::House => 'H',
+            CellType::Office => 'O',
+            CellType::Park => 'P',
+            CellType::EnergyStation => 'E',
+            CellType::Empty => '.',
+        }
+    }
+
+    pub fn from_glyph(glyph: char) -> Option<CellType> {
+        CellType::ALL.into_iter().find(|t| t.glyph() == glyph)
+    }
+}
+
 impl fmt::Display for CellType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.glyph())
+    }
+}
+
+/// travel direction on the grid, north is towards y = 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::South,
+        Direction::East,
+        Direction::North,
+        Direction::West,
+    ];
+
+    pub fn offset(&self) -> (isize, isize) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::East => (1, 0),
+            Direction::South => (0, 1),
+            Direction::West => (-1, 0),
+        }
+    }
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+
+    pub fn left(&self) -> Direction {
         match self {
-            CellType::Road => write!(f, "R"),
-            CellType::House => write!(f, "H"),
-            CellType::Office => write!(f, "O"),
-            CellType::Park => write!(f, "P"),
-            CellType::EnergyStation => write!(f, "E"),
-            CellType::Empty => write!(f, "."),
+            Direction::North => Direction::West,
+            Direction::East => Direction::North,
+            Direction::South => Direction::East,
+            Direction::West => Direction::South,
+        }
+    }
+
+    /// direction of a single step between adjacent positions
+    pub fn between(from: &Position, to: &Position) -> Option<Direction> {
+        Direction::ALL
+            .into_iter()
+            .find(|d| from.step(*d).as_ref() == Some(to))
+    }
+}
+
+/// turn relative to the heading an agent entered a cell with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Turn {
+    Left,
+    Right,
+    Reverse, // u-turn
+}
+
+impl Turn {
+    /// `None` when going straight
+    pub fn between(heading: Direction, exit: Direction) -> Option<Turn> {
+        if exit == heading {
+            None
+        } else if exit == heading.opposite() {
+            Some(Turn::Reverse)
+        } else if exit == heading.left() {
+            Some(Turn::Left)
+        } else {
+            Some(Turn::Right)
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
@@ -35,21 +154,18 @@ impl Position {
         Self { x, y }
     }
 
+    /// adjacent position, `None` if it would be negative
+    pub fn step(&self, direction: Direction) -> Option<Position> {
+        let (dx, dy) = direction.offset();
+        Some(Position::new(
+            self.x.checked_add_signed(dx)?,
+            self.y.checked_add_signed(dy)?,
+        ))
+    }
+
     /// Manhattan distance
     pub fn distance(&self, other: &Position) -> usize {
-        let x_diff = if self.x > other.x {
-            self.x - other.x
-        } else {
-            other.x - self.x
-        };
-
-        let y_diff = if self.y > other.y {
-            self.y - other.y
-        } else {
-            other.y - self.y
-        };
-
-        x_diff + y_diff
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
     }
 }
 
@@ -57,7 +173,26 @@ impl Position {
 pub struct Cell {
     pub cell_type: CellType,
     pub position: Position,
-    pub occupants: Vec<String>, // agents occupying this cell
+    pub occupants: usize,         // agents occupying this cell
+    pub capacity: Option<usize>,  // max occupants, `None` is unlimited
+    pub speed_limit: Option<f64>, // cells per tick, `None` is unlimited
+    pub lanes: usize,
+    /// residents of a house, jobs at an office or charging slots at an energy station,
+    /// `None` is unlimited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub places: Option<usize>,
+    /// directions road traffic may leave this cell in, `None` is all of them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exits: Option<Vec<Direction>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub banned_turns: Vec<Turn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<SignalPlan>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub observed_occupancy: f64, // moving average of occupant count
+    #[serde(skip)]
+    pub revision: u64, // grid revision of the last change to this cell
 }
 
 impl Cell {
@@ -65,19 +200,81 @@ impl Cell {
         Self {
             cell_type,
             position,
-            occupants: Vec::new(),
+            occupants: 0,
+            capacity: cell_type.default_capacity(),
+            speed_limit: None,
+            lanes: 1,
+            places: cell_type.default_places(),
+            exits: None,
+            banned_turns: Vec::new(),
+            signal: None,
+            name: None,
+            observed_occupancy: 0.0,
+            revision: 0,
+        }
+    }
+
+    /// whether agents can move into this cell at all
+    pub fn is_open(&self) -> bool {
+        self.cell_type != CellType::Empty && self.speed_limit.is_none_or(|limit| limit > 0.0)
+    }
+
+    /// ticks an agent moving at `speed` needs to cross this cell, capped by the speed limit
+    pub fn travel_time(&self, speed: f64) -> f64 {
+        1.0 / self.limit_speed(speed)
+    }
+
+    /// speed of an agent moving into this cell, capped by the speed limit
+    pub fn limit_speed(&self, speed: f64) -> f64 {
+        match self.speed_limit {
+            Some(limit) => speed.min(limit),
+            None => speed,
+        }
+    }
+
+    /// whether the signal lets traffic heading `heading` in, always true without a signal
+    pub fn is_green(&self, now: WorldTime, heading: Direction) -> bool {
+        self.signal
+            .as_ref()
+            .is_none_or(|signal| signal.is_green(now, heading))
+    }
+
+    /// whether an agent that entered with `heading` may leave towards `exit`
+    pub fn allows_exit(&self, exit: Direction, heading: Option<Direction>) -> bool {
+        if self
+            .exits
+            .as_ref()
+            .is_some_and(|exits| !exits.contains(&exit))
+        {
+            return false;
+        }
+        match heading.and_then(|heading| Turn::between(heading, exit)) {
+            Some(turn) => !self.banned_turns.contains(&turn),
+            None => true,
         }
     }
 
     pub fn occupant_count(&self) -> usize {
-        self.occupants.len()
+        self.occupants
+    }
+
+    /// no agent can enter until someone leaves
+    pub fn is_full(&self) -> bool {
+        self.capacity
+            .is_some_and(|capacity| self.occupants >= capacity)
+    }
+
+    pub fn add_occupant(&mut self) {
+        self.occupants += 1;
     }
 
-    pub fn add_occupant(&mut self, agent_id: &str) {
-        self.occupants.push(agent_id.to_owned());
+    /// blend the current occupant count into `observed_occupancy`
+    pub fn observe_occupancy(&mut self, smoothing: f64) {
+        self.observed_occupancy =
+            smoothing * self.occupants as f64 + (1.0 - smoothing) * self.observed_occupancy;
     }
 
-    pub fn remove_occupant(&mut self, agent_id: &str) {
-        self.occupants.retain(|id| id != agent_id);
+    pub fn remove_occupant(&mut self) {
+        self.occupants = self.occupants.saturating_sub(1);
     }
 }
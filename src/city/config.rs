@@ -1,21 +1,91 @@
-use crate::city::cell::{CellType, Position};
+use crate::city::cell::{Cell, CellType, Direction, Position, Turn};
 use crate::city::grid::CityGrid;
+use crate::city::signal::SignalPlan;
+use crate::city::transit::TransitLine;
+use crate::city::validation::{Issue, Severity};
+use crate::simulation::simulation::WorldTime;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
+use std::str::FromStr;
 
 const ROAD: &str = "Road";
 const HOUSE: &str = "House";
 const OFFICE: &str = "Office";
 const PARK: &str = "Park";
 const ENERGY_STATION: &str = "EnergyStation";
+const EMPTY: &str = "Empty";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CellConfig {
     pub x: usize,
     pub y: usize,
     pub cell_type: String,
+    /// overrides the capacity of this cell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<usize>,
+    /// cells per tick, unlimited if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed_limit: Option<f64>,
+    /// multiplies the type capacity unless `capacity` is set, 1 if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lanes: Option<usize>,
+    /// residents of a house, jobs at an office or charging slots at an energy station,
+    /// overrides the type default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub places: Option<usize>,
+    /// directions road traffic may leave this cell in, e.g. `["East"]` for
+    /// a one-way street, all directions if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exits: Option<Vec<Direction>>,
+    /// turns not allowed on this cell, relative to the entry heading
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub banned_turns: Option<Vec<Turn>>,
+    /// traffic signal controlling entry into this cell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<SignalPlan>,
+    /// display name, e.g. the street name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl CellConfig {
+    pub fn new(x: usize, y: usize, cell_type: &str) -> Self {
+        Self {
+            x,
+            y,
+            cell_type: cell_type.to_string(),
+            capacity: None,
+            speed_limit: None,
+            lanes: None,
+            places: None,
+            exits: None,
+            banned_turns: None,
+            signal: None,
+            name: None,
+        }
+    }
+
+    /// set the attributes of `cell` from this config, the capacity is the
+    /// explicit one or the current capacity of `cell` times the lanes
+    pub fn apply_to(&self, cell: &mut Cell) {
+        cell.lanes = self.lanes.unwrap_or(1).max(1);
+        cell.speed_limit = self.speed_limit;
+        cell.exits = self.exits.clone();
+        cell.banned_turns = self.banned_turns.clone().unwrap_or_default();
+        cell.signal = self.signal.clone();
+        cell.name = self.name.clone();
+        cell.capacity = match self.capacity {
+            Some(capacity) => Some(capacity),
+            None => cell.capacity.map(|capacity| capacity * cell.lanes),
+        };
+        if self.places.is_some() {
+            cell.places = self.places;
+        }
+    }
 }
 
 /// city map
@@ -24,131 +94,235 @@ pub struct CityConfig {
     pub width: usize,
     pub height: usize,
     pub cells: Vec<CellConfig>,
+    /// capacity per cell type name, overrides `CellType::default_capacity`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub capacities: BTreeMap<String, usize>,
+    /// places per cell type name, overrides `CellType::default_places`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub places: BTreeMap<String, usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<TransitLine>,
 }
 
 impl CityConfig {
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
-        let file = File::open(path).map_err(|e| format!("failed to open config file: {}", e))?;
+    /// load a JSON map, or a text map if the file ends in `.txt` or `.map`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        if is_ascii_map(path.as_ref()) {
+            let text = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+            Self::from_ascii(&text)
+        } else {
+            Self::from_file(path)
+        }
+    }
+
+    /// save as JSON, or as a text map if the file ends in `.txt` or `.map`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        if is_ascii_map(path.as_ref()) {
+            std::fs::write(path, self.to_ascii()?).map_err(ConfigError::Io)
+        } else {
+            self.save_to_file(path)
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let file = File::open(path).map_err(ConfigError::Io)?;
         let reader = BufReader::new(file);
-        serde_json::from_reader(reader).map_err(|e| format!("failed to parse config: {}", e))
+        serde_json::from_reader(reader).map_err(ConfigError::Json)
     }
 
     pub fn default() -> Self {
         let mut cells = Vec::new();
 
-        for x in 0..10 {
-            cells.push(CellConfig {
-                x,
-                y: 2,
-                cell_type: ROAD.to_string(),
-            });
-            cells.push(CellConfig {
-                x,
-                y: 5,
-                cell_type: ROAD.to_string(),
-            });
-            cells.push(CellConfig {
-                x,
-                y: 8,
-                cell_type: ROAD.to_string(),
-            });
-        }
-
-        for y in 0..10 {
-            cells.push(CellConfig {
-                x: 1,
-                y,
-                cell_type: ROAD.to_string(),
-            });
-            cells.push(CellConfig {
-                x: 4,
-                y,
-                cell_type: ROAD.to_string(),
-            });
-            cells.push(CellConfig {
-                x: 7,
-                y,
-                cell_type: ROAD.to_string(),
-            });
+        // roads at x = 1/4/7 and y = 2/5/8, the intersections get signals
+        const ROAD_LINES: [usize; 3] = [1, 4, 7];
+        const STREET_LINES: [usize; 3] = [2, 5, 8];
+        for x in (0..10).filter(|x| !ROAD_LINES.contains(x)) {
+            for y in STREET_LINES {
+                cells.push(CellConfig::new(x, y, ROAD));
+            }
         }
 
-        for x in 2..4 {
-            for y in 0..2 {
+        for y in (0..10).filter(|y| !STREET_LINES.contains(y)) {
+            for x in ROAD_LINES {
+                cells.push(CellConfig::new(x, y, ROAD));
+            }
+        }
+
+        // green waves along x
+        for (i, x) in ROAD_LINES.into_iter().enumerate() {
+            for y in STREET_LINES {
                 cells.push(CellConfig {
-                    x,
-                    y,
-                    cell_type: HOUSE.to_string(),
+                    signal: Some(SignalPlan::two_phase(10, 3 * i as WorldTime)),
+                    ..CellConfig::new(x, y, ROAD)
                 });
             }
         }
 
+        for x in 2..4 {
+            for y in 0..2 {
+                cells.push(CellConfig::new(x, y, HOUSE));
+            }
+        }
+
         for x in 8..10 {
             for y in 6..8 {
-                cells.push(CellConfig {
-                    x,
-                    y,
-                    cell_type: OFFICE.to_string(),
-                });
+                cells.push(CellConfig::new(x, y, OFFICE));
             }
         }
 
-        cells.push(CellConfig {
-            x: 9,
-            y: 0,
-            cell_type: ENERGY_STATION.to_string(),
-        });
-        cells.push(CellConfig {
-            x: 0,
-            y: 9,
-            cell_type: ENERGY_STATION.to_string(),
-        });
-
-        cells.push(CellConfig {
-            x: 5,
-            y: 3,
-            cell_type: PARK.to_string(),
-        });
-        cells.push(CellConfig {
-            x: 5,
-            y: 4,
-            cell_type: PARK.to_string(),
-        });
-        cells.push(CellConfig {
-            x: 6,
-            y: 3,
-            cell_type: PARK.to_string(),
-        });
-        cells.push(CellConfig {
-            x: 6,
-            y: 4,
-            cell_type: PARK.to_string(),
-        });
+        cells.push(CellConfig::new(9, 0, ENERGY_STATION));
+        cells.push(CellConfig::new(0, 9, ENERGY_STATION));
+
+        cells.push(CellConfig::new(5, 3, PARK));
+        cells.push(CellConfig::new(5, 4, PARK));
+        cells.push(CellConfig::new(6, 3, PARK));
+        cells.push(CellConfig::new(6, 4, PARK));
+
+        // a bus from the houses to the offices
+        let lines = vec![TransitLine {
+            name: "A".to_string(),
+            stops: vec![
+                Position::new(4, 1),
+                Position::new(4, 5),
+                Position::new(7, 5),
+                Position::new(7, 7),
+            ],
+            ..TransitLine::default()
+        }];
 
         Self {
             width: 10,
             height: 10,
             cells,
+            capacities: BTreeMap::new(),
+            places: BTreeMap::new(),
+            lines,
         }
     }
 
-    pub fn to_city_grid(&self) -> CityGrid {
+    /// the grid the config describes, `Err` with the unknown cell types and
+    /// cells out of bounds `validate_cells` reports
+    pub fn to_city_grid(&self) -> Result<CityGrid, ConfigError> {
+        let errors = self
+            .validate_cells()
+            .into_iter()
+            .filter(|issue| issue.severity() == Severity::Error)
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            return Err(ConfigError::Invalid(errors));
+        }
+        // every cell type name is known and every cell in bounds from here on
         let mut grid = CityGrid::new(self.width, self.height);
         for cell_config in &self.cells {
             let position = Position::new(cell_config.x, cell_config.y);
             let cell_type = CellType::from(&cell_config.cell_type);
-            let _ = grid.set_cell_type(&position, cell_type);
+            grid.set_cell_type(&position, cell_type).map_err(|_| {
+                ConfigError::Invalid(vec![Issue::OutOfBounds {
+                    x: cell_config.x,
+                    y: cell_config.y,
+                }])
+            })?;
+        }
+        for (cell_type, capacity) in &self.capacities {
+            grid.set_type_capacity(CellType::from(cell_type), Some(*capacity));
+        }
+        for (cell_type, places) in &self.places {
+            grid.set_type_places(CellType::from(cell_type), Some(*places));
+        }
+        for cell_config in &self.cells {
+            let position = Position::new(cell_config.x, cell_config.y);
+            if let Some(cell) = grid.get_cell_mut(&position) {
+                cell_config.apply_to(cell);
+            }
+        }
+        grid.lines = self.lines.clone();
+        Ok(grid)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        let file = File::create(path).map_err(ConfigError::Io)?;
+        serde_json::to_writer_pretty(file, self).map_err(ConfigError::Json)
+    }
+}
+
+/// failure to read or write a city config
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// malformed text map
+    Map {
+        line: usize,
+        message: String,
+    },
+    /// the config loaded but failed validation
+    Invalid(Vec<Issue>),
+    /// the city cannot be written as a text map
+    Unrepresentable(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "failed to access config file: {}", e),
+            ConfigError::Json(e) => write!(f, "failed to parse config: {}", e),
+            ConfigError::Map { line, message } => write!(f, "line {}: {}", line, message),
+            ConfigError::Unrepresentable(message) => {
+                write!(f, "cannot write a text map: {}", message)
+            }
+            ConfigError::Invalid(issues) => {
+                write!(f, "invalid city config:")?;
+                for issue in issues {
+                    write!(f, "\n  {:?}: {}", issue.severity(), issue)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(e) => Some(e),
+            ConfigError::Json(e) => Some(e),
+            _ => None,
         }
-        grid
     }
+}
+
+fn is_ascii_map(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("txt") | Some("map")
+    )
+}
+
+/// config name of a cell type, inverse of `CellType::from`
+pub fn cell_type_name(cell_type: CellType) -> &'static str {
+    match cell_type {
+        CellType::Road => ROAD,
+        CellType::House => HOUSE,
+        CellType::Office => OFFICE,
+        CellType::Park => PARK,
+        CellType::EnergyStation => ENERGY_STATION,
+        CellType::Empty => EMPTY,
+    }
+}
+
+impl FromStr for CellType {
+    type Err = ();
 
-    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
-        let file =
-            File::create(path).map_err(|e| format!("failed to create config file: {}", e))?;
-        serde_json::to_writer_pretty(file, self)
-            .map_err(|e| format!("failed to write config: {}", e))
+    /// strict variant of `CellType::from`, unknown names are an error
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CellType::ALL
+            .into_iter()
+            .find(|t| cell_type_name(*t) == s)
+            .ok_or(())
     }
 }
 
+/// lenient conversion, unknown names become `Empty`
 impl<T: AsRef<str>> From<T> for CellType {
     fn from(value: T) -> Self {
         match value.as_ref() {
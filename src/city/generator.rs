@@ -0,0 +1,285 @@
+use crate::city::cell::{CellType, Direction, Position};
+use crate::city::config::{cell_type_name, CellConfig, CityConfig};
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+pub enum RoadPattern {
+    /// evenly spaced streets and avenues
+    Grid,
+    /// concentric ring roads connected by spokes from the center
+    Radial,
+    /// random streets branching off each other
+    Organic,
+}
+
+/// relative shares of building lots, normalized when generating
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Zoning {
+    pub house: f64,
+    pub office: f64,
+    pub park: f64,
+    pub empty: f64,
+}
+
+impl Default for Zoning {
+    fn default() -> Self {
+        Self {
+            house: 0.5,
+            office: 0.25,
+            park: 0.1,
+            empty: 0.15,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneratorConfig {
+    pub width: usize,
+    pub height: usize,
+    pub seed: u64,
+    pub pattern: RoadPattern,
+    /// cells between parallel roads
+    pub road_spacing: usize,
+    /// zoning of lots next to a road, lots away from roads become parks or stay empty
+    pub zoning: Zoning,
+    /// energy stations per 1000 cells
+    pub energy_station_density: f64,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            width: 50,
+            height: 50,
+            seed: 0,
+            pattern: RoadPattern::Grid,
+            road_spacing: 4,
+            zoning: Zoning::default(),
+            energy_station_density: 2.0,
+        }
+    }
+}
+
+/// seeded generator, the same config always produces the same city
+pub fn generate(config: &GeneratorConfig) -> CityConfig {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let (width, height) = (config.width.max(1), config.height.max(1));
+    let spacing = config.road_spacing.max(2);
+    let mut grid = vec![vec![CellType::Empty; width]; height];
+
+    match config.pattern {
+        RoadPattern::Grid => grid_roads(&mut grid, spacing),
+        RoadPattern::Radial => radial_roads(&mut grid, spacing),
+        RoadPattern::Organic => organic_roads(&mut grid, spacing, &mut rng),
+    }
+    zone_lots(&mut grid, &config.zoning, &mut rng);
+    place_energy_stations(&mut grid, config.energy_station_density, &mut rng);
+    ensure_type(&mut grid, CellType::House, &mut rng);
+    ensure_type(&mut grid, CellType::Office, &mut rng);
+
+    let mut cells = Vec::new();
+    for (y, row) in grid.iter().enumerate() {
+        for (x, cell_type) in row.iter().enumerate() {
+            if *cell_type != CellType::Empty {
+                cells.push(CellConfig::new(x, y, cell_type_name(*cell_type)));
+            }
+        }
+    }
+    CityConfig {
+        width,
+        height,
+        cells,
+        capacities: BTreeMap::new(),
+        places: BTreeMap::new(),
+        lines: Vec::new(),
+    }
+}
+
+fn grid_roads(grid: &mut [Vec<CellType>], spacing: usize) {
+    let offset = spacing / 2;
+    for (y, row) in grid.iter_mut().enumerate() {
+        for (x, cell) in row.iter_mut().enumerate() {
+            if x % spacing == offset || y % spacing == offset {
+                *cell = CellType::Road;
+            }
+        }
+    }
+}
+
+fn radial_roads(grid: &mut [Vec<CellType>], spacing: usize) {
+    let height = grid.len();
+    let width = grid[0].len();
+    let center = Position::new(width / 2, height / 2);
+
+    // square rings around the center
+    for (y, row) in grid.iter_mut().enumerate() {
+        for (x, cell) in row.iter_mut().enumerate() {
+            let ring = x.abs_diff(center.x).max(y.abs_diff(center.y));
+            if ring > 0 && ring % spacing == 0 {
+                *cell = CellType::Road;
+            }
+        }
+    }
+
+    // straight spokes along both axes
+    for row in grid.iter_mut() {
+        row[center.x] = CellType::Road;
+    }
+    for cell in grid[center.y].iter_mut() {
+        *cell = CellType::Road;
+    }
+
+    // diagonal spokes as staircases, a cell at a time so they stay 4-connected
+    for (dx, dy) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+        let mut p = center;
+        let mut horizontal = true;
+        loop {
+            let next = if horizontal {
+                p.x.checked_add_signed(dx).map(|x| Position::new(x, p.y))
+            } else {
+                p.y.checked_add_signed(dy).map(|y| Position::new(p.x, y))
+            };
+            match next {
+                Some(next) if next.x < width && next.y < height => {
+                    grid[next.y][next.x] = CellType::Road;
+                    p = next;
+                    horizontal = !horizontal;
+                }
+                _ => break,
+            }
+        }
+    }
+}
+
+fn organic_roads(grid: &mut [Vec<CellType>], spacing: usize, rng: &mut StdRng) {
+    let height = grid.len();
+    let width = grid[0].len();
+    let target = width * height / spacing;
+    let mut roads = vec![Position::new(width / 2, height / 2)];
+    grid[height / 2][width / 2] = CellType::Road;
+
+    // every street starts on an existing road, so the network stays connected
+    let mut attempts = 0;
+    while roads.len() < target && attempts < target * 4 {
+        attempts += 1;
+        let mut p = roads[rng.random_range(0..roads.len())];
+        let mut direction = *Direction::ALL.choose(rng).unwrap_or(&Direction::East);
+        let length = rng.random_range(spacing..=spacing * 3);
+        for _ in 0..length {
+            if rng.random_bool(0.15) {
+                direction = if rng.random_bool(0.5) {
+                    direction.left()
+                } else {
+                    direction.left().opposite()
+                };
+            }
+            let Some(next) = p.step(direction).filter(|n| n.x < width && n.y < height) else {
+                break;
+            };
+            // keep a lot between parallel streets
+            if grid[next.y][next.x] != CellType::Road {
+                let side = direction.left();
+                let crowded = [side, side.opposite()].into_iter().any(|d| {
+                    next.step(d)
+                        .and_then(|n| n.step(direction))
+                        .filter(|n| n.x < width && n.y < height)
+                        .is_some_and(|n| grid[n.y][n.x] == CellType::Road)
+                });
+                if crowded {
+                    break;
+                }
+                grid[next.y][next.x] = CellType::Road;
+                roads.push(next);
+            }
+            p = next;
+        }
+    }
+}
+
+fn is_next_to_road(grid: &[Vec<CellType>], p: Position) -> bool {
+    Direction::ALL.into_iter().any(|d| {
+        p.step(d)
+            .and_then(|n| grid.get(n.y)?.get(n.x))
+            .is_some_and(|t| *t == CellType::Road)
+    })
+}
+
+fn zone_lots(grid: &mut [Vec<CellType>], zoning: &Zoning, rng: &mut StdRng) {
+    let weights = [
+        (CellType::House, zoning.house.max(0.0)),
+        (CellType::Office, zoning.office.max(0.0)),
+        (CellType::Park, zoning.park.max(0.0)),
+        (CellType::Empty, zoning.empty.max(0.0)),
+    ];
+    let total: f64 = weights.iter().map(|(_, w)| w).sum();
+    if total <= 0.0 {
+        return;
+    }
+    for y in 0..grid.len() {
+        for x in 0..grid[y].len() {
+            if grid[y][x] != CellType::Empty {
+                continue;
+            }
+            grid[y][x] = if is_next_to_road(grid, Position::new(x, y)) {
+                let mut pick = rng.random_range(0.0..total);
+                let mut zoned = CellType::Empty;
+                for (cell_type, weight) in weights {
+                    if pick < weight {
+                        zoned = cell_type;
+                        break;
+                    }
+                    pick -= weight;
+                }
+                zoned
+            } else {
+                // every destination needs road access, back lots stay empty
+                CellType::Empty
+            };
+        }
+    }
+}
+
+fn place_energy_stations(grid: &mut [Vec<CellType>], density: f64, rng: &mut StdRng) {
+    let cells = grid.len() * grid[0].len();
+    let count = (cells as f64 * density.max(0.0) / 1000.0).round() as usize;
+    let mut lots = road_side_lots(grid, |t| t != CellType::Road);
+    for _ in 0..count {
+        if lots.is_empty() {
+            break;
+        }
+        let p = lots.swap_remove(rng.random_range(0..lots.len()));
+        grid[p.y][p.x] = CellType::EnergyStation;
+    }
+}
+
+/// make sure agents can be placed, converting a road-side lot if needed
+fn ensure_type(grid: &mut [Vec<CellType>], cell_type: CellType, rng: &mut StdRng) {
+    if grid.iter().flatten().any(|t| *t == cell_type) {
+        return;
+    }
+    let lots = road_side_lots(grid, |t| {
+        t != CellType::Road && t != CellType::House && t != CellType::Office
+    });
+    if let Some(p) = lots.choose(rng) {
+        grid[p.y][p.x] = cell_type;
+    }
+}
+
+fn road_side_lots(grid: &[Vec<CellType>], filter: impl Fn(CellType) -> bool) -> Vec<Position> {
+    let mut lots = Vec::new();
+    for (y, row) in grid.iter().enumerate() {
+        for (x, cell_type) in row.iter().enumerate() {
+            let p = Position::new(x, y);
+            if filter(*cell_type) && is_next_to_road(grid, p) {
+                lots.push(p);
+            }
+        }
+    }
+    lots
+}
@@ -1,49 +1,125 @@
-use crate::city::cell::{Cell, CellType, Position};
-use serde::{Deserialize, Serialize};
+use crate::city::cell::{Cell, CellType, Direction, Position};
+use crate::city::signal::SignalState;
+use crate::city::transit::TransitLine;
+use crate::simulation::simulation::WorldTime;
+use std::collections::BTreeSet;
 use std::fmt;
 use std::fmt::{Display, Write};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
 pub struct CityGrid {
     pub width: usize,
     pub height: usize,
-    pub cells: Vec<Vec<Cell>>,
+    cells: Vec<Cell>,                                  // row by row
+    cells_by_type: [BTreeSet<usize>; CellType::COUNT], // cell indexes per type
+    revision: u64,                                     // bumped on every map change
+    routes_revision: u64, // routes planned before this revision are stale
+    type_capacity: [Option<usize>; CellType::COUNT], // capacity a cell of each type starts with
+    type_places: [Option<usize>; CellType::COUNT], // places a cell of each type starts with
+    pub lines: Vec<TransitLine>,
 }
 
 impl CityGrid {
     pub fn new(width: usize, height: usize) -> Self {
-        let cells = (0..height)
-            .map(|y| {
-                (0..width)
-                    .map(|x| {
-                        let position = Position::new(x, y);
-                        Cell::new(CellType::Empty, position)
-                    })
-                    .collect()
-            })
-            .collect();
+        let cells = (0..width * height)
+            .map(|i| Cell::new(CellType::Empty, Position::new(i % width, i / width)))
+            .collect::<Vec<_>>();
+        let mut cells_by_type: [BTreeSet<usize>; CellType::COUNT] = Default::default();
+        cells_by_type[CellType::Empty as usize] = (0..cells.len()).collect();
 
         Self {
             width,
             height,
             cells,
+            cells_by_type,
+            revision: 0,
+            routes_revision: 0,
+            type_capacity: CellType::ALL.map(|cell_type| cell_type.default_capacity()),
+            type_places: CellType::ALL.map(|cell_type| cell_type.default_places()),
+            lines: Vec::new(),
         }
     }
 
+    /// index into `cells()` of an in-bounds position
+    pub fn index(&self, position: &Position) -> Option<usize> {
+        (position.y < self.height && position.x < self.width)
+            .then_some(position.y * self.width + position.x)
+    }
+
+    /// every cell, row by row
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
     pub fn get_cell(&self, position: &Position) -> Option<&Cell> {
-        if position.y < self.height && position.x < self.width {
-            Some(&self.cells[position.y][position.x])
-        } else {
-            None
-        }
+        Some(&self.cells[self.index(position)?])
     }
 
     pub fn get_cell_mut(&mut self, position: &Position) -> Option<&mut Cell> {
-        if position.y < self.height && position.x < self.width {
-            Some(&mut self.cells[position.y][position.x])
-        } else {
-            None
+        let index = self.index(position)?;
+        Some(&mut self.cells[index])
+    }
+
+    /// in-bounds cell one step away in `direction`
+    pub fn step(&self, position: &Position, direction: Direction) -> Option<Position> {
+        position
+            .step(direction)
+            .filter(|p| p.x < self.width && p.y < self.height)
+    }
+
+    /// whether moving out of `from` towards `direction` respects one-way roads and
+    /// turn restrictions, `heading` is the direction `from` was entered with
+    ///
+    /// restrictions only apply between road cells, driveways into and out of
+    /// buildings are always allowed
+    pub fn can_move(
+        &self,
+        from: &Position,
+        direction: Direction,
+        heading: Option<Direction>,
+    ) -> bool {
+        let Some(to) = self.step(from, direction).and_then(|p| self.get_cell(&p)) else {
+            return false;
+        };
+        let Some(from) = self.get_cell(from) else {
+            return false;
+        };
+        if from.cell_type != CellType::Road || to.cell_type != CellType::Road {
+            return true;
         }
+        from.allows_exit(direction, heading)
+    }
+
+    /// the part of `heading` that matters when leaving `position`: only turn
+    /// restrictions depend on it, so routers can merge states elsewhere
+    pub fn effective_heading(
+        &self,
+        position: &Position,
+        heading: Option<Direction>,
+    ) -> Option<Direction> {
+        heading.filter(|_| {
+            self.get_cell(position)
+                .is_some_and(|cell| !cell.banned_turns.is_empty())
+        })
+    }
+
+    /// directions and positions reachable in one legal step
+    pub fn moves(
+        &self,
+        position: &Position,
+        heading: Option<Direction>,
+    ) -> impl Iterator<Item = (Direction, Position)> + '_ {
+        let position = *position;
+        Direction::ALL.into_iter().filter_map(move |direction| {
+            if !self.can_move(&position, direction, heading) {
+                return None;
+            }
+            Some((direction, self.step(&position, direction)?))
+        })
     }
 
     pub fn set_cell_type(
@@ -51,8 +127,19 @@ impl CityGrid {
         position: &Position,
         cell_type: CellType,
     ) -> Result<(), String> {
-        if let Some(cell) = self.get_cell_mut(position) {
-            cell.cell_type = cell_type;
+        if let Some(index) = self.index(position) {
+            let cell = &mut self.cells[index];
+            let previous = cell.cell_type;
+            if previous != cell_type {
+                cell.cell_type = cell_type;
+                cell.capacity = self.type_capacity[cell_type as usize];
+                cell.places = self.type_places[cell_type as usize];
+                self.cells_by_type[previous as usize].remove(&index);
+                self.cells_by_type[cell_type as usize].insert(index);
+                // routes through the cell are stale, callers that know the passability
+                // rules invalidate every route when the cell opens a shorter one
+                self.invalidate_cell(position);
+            }
             Ok(())
         } else {
             Err(format!(
@@ -62,21 +149,95 @@ impl CityGrid {
         }
     }
 
-    pub fn find_cells_of_type(&self, cell_type: CellType) -> Vec<Position> {
-        let mut positions = Vec::new();
-
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let position = Position::new(x, y);
-                if let Some(cell) = self.get_cell(&position) {
-                    if cell.cell_type == cell_type {
-                        positions.push(position);
-                    }
-                }
-            }
+    /// capacity of a cell of this type unless configured per cell, `None` is unlimited
+    pub fn type_capacity(&self, cell_type: CellType) -> Option<usize> {
+        self.type_capacity[cell_type as usize]
+    }
+
+    /// places of a cell of this type unless configured per cell, `None` is unlimited
+    pub fn type_places(&self, cell_type: CellType) -> Option<usize> {
+        self.type_places[cell_type as usize]
+    }
+
+    /// override the capacity of a cell type, for the cells of that type now and later
+    pub fn set_type_capacity(&mut self, cell_type: CellType, capacity: Option<usize>) {
+        self.type_capacity[cell_type as usize] = capacity;
+        for &index in &self.cells_by_type[cell_type as usize] {
+            self.cells[index].capacity = capacity;
+        }
+    }
+
+    /// override the places of a cell type, for the cells of that type now and later
+    pub fn set_type_places(&mut self, cell_type: CellType, places: Option<usize>) {
+        self.type_places[cell_type as usize] = places;
+        for &index in &self.cells_by_type[cell_type as usize] {
+            self.cells[index].places = places;
         }
+    }
 
-        positions
+    /// update the moving average of occupancy on every cell
+    pub fn observe_occupancy(&mut self, smoothing: f64) {
+        for cell in &mut self.cells {
+            cell.observe_occupancy(smoothing);
+        }
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// mark a cell as changed, routes passing through it are stale
+    pub fn invalidate_cell(&mut self, position: &Position) {
+        self.revision += 1;
+        let revision = self.revision;
+        if let Some(cell) = self.get_cell_mut(position) {
+            cell.revision = revision;
+        }
+    }
+
+    /// mark every route planned so far as stale
+    pub fn invalidate_routes(&mut self) {
+        self.revision += 1;
+        self.routes_revision = self.revision;
+    }
+
+    /// whether a path planned at `planned_at` is still usable
+    pub fn is_route_valid<'a>(
+        &self,
+        path: impl IntoIterator<Item = &'a Position>,
+        planned_at: u64,
+    ) -> bool {
+        if planned_at >= self.revision {
+            return true;
+        }
+        if planned_at < self.routes_revision {
+            return false;
+        }
+        path.into_iter().all(|p| {
+            self.get_cell(p)
+                .is_some_and(|cell| cell.revision <= planned_at)
+        })
+    }
+
+    /// current state of every signal on the map
+    pub fn signal_states(&self, now: WorldTime) -> Vec<SignalState> {
+        self.cells
+            .iter()
+            .filter_map(|cell| SignalState::at(cell.position, cell.signal.as_ref()?, now))
+            .collect()
+    }
+
+    /// cells of a type in row order, from the per-type index
+    pub fn cells_of_type(&self, cell_type: CellType) -> impl Iterator<Item = &Cell> {
+        self.cells_by_type[cell_type as usize]
+            .iter()
+            .map(|i| &self.cells[*i])
+    }
+
+    pub fn find_cells_of_type(&self, cell_type: CellType) -> Vec<Position> {
+        self.cells_of_type(cell_type)
+            .map(|cell| cell.position)
+            .collect()
     }
 
     pub fn find_nearest_cell_with_type(
@@ -84,20 +245,18 @@ impl CityGrid {
         from: &Position,
         cell_type: CellType,
     ) -> Option<Position> {
-        let cells_of_type = self.find_cells_of_type(cell_type);
-
-        cells_of_type
-            .into_iter()
+        self.cells_of_type(cell_type)
+            .map(|cell| cell.position)
             .min_by_key(|pos| from.distance(pos))
     }
 }
 
 impl Display for CityGrid {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for row in &self.cells {
+        for row in self.rows() {
             for cell in row {
-                let display = if !cell.occupants.is_empty() {
-                    format!("{}", cell.occupants.len())
+                let display = if cell.occupants > 0 {
+                    format!("{}", cell.occupants)
                 } else {
                     format!("{}", cell.cell_type)
                 };
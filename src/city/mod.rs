@@ -1,3 +1,8 @@
+pub mod ascii;
 pub mod cell;
 pub mod config;
+pub mod generator;
 pub mod grid;
+pub mod signal;
+pub mod transit;
+pub mod validation;
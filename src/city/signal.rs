@@ -0,0 +1,88 @@
+use crate::city::cell::{Direction, Position};
+use crate::simulation::simulation::WorldTime;
+use serde::{Deserialize, Serialize};
+
+/// one step of a signal cycle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalPhase {
+    /// headings allowed to enter the intersection, every other approach is red
+    pub green: Vec<Direction>,
+    /// ticks
+    pub duration: WorldTime,
+}
+
+/// fixed-time signal plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalPlan {
+    pub phases: Vec<SignalPhase>,
+    /// ticks the cycle is shifted by, coordinates neighboring signals
+    #[serde(default)]
+    pub offset: WorldTime,
+}
+
+impl SignalPlan {
+    /// two phases: north-south green, then east-west green
+    pub fn two_phase(green_ticks: WorldTime, offset: WorldTime) -> Self {
+        Self {
+            phases: vec![
+                SignalPhase {
+                    green: vec![Direction::North, Direction::South],
+                    duration: green_ticks,
+                },
+                SignalPhase {
+                    green: vec![Direction::East, Direction::West],
+                    duration: green_ticks,
+                },
+            ],
+            offset,
+        }
+    }
+
+    pub fn cycle_length(&self) -> WorldTime {
+        self.phases.iter().map(|p| p.duration.max(0)).sum()
+    }
+
+    /// index of the active phase, `None` if the plan has no phases
+    pub fn phase_at(&self, now: WorldTime) -> Option<usize> {
+        let cycle = self.cycle_length();
+        if cycle == 0 {
+            return None;
+        }
+        let mut t = (now + self.offset).rem_euclid(cycle);
+        for (i, phase) in self.phases.iter().enumerate() {
+            let duration = phase.duration.max(0);
+            if t < duration {
+                return Some(i);
+            }
+            t -= duration;
+        }
+        None
+    }
+
+    /// whether traffic heading `heading` may enter now, an empty plan is always green
+    pub fn is_green(&self, now: WorldTime, heading: Direction) -> bool {
+        match self.phase_at(now) {
+            Some(i) => self.phases[i].green.contains(&heading),
+            None => true,
+        }
+    }
+}
+
+/// signal state sent along with each tick update
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalState {
+    pub position: Position,
+    pub phase: usize,
+    pub green: Vec<Direction>,
+}
+
+impl SignalState {
+    pub fn at(position: Position, plan: &SignalPlan, now: WorldTime) -> Option<Self> {
+        let phase = plan.phase_at(now)?;
+        Some(Self {
+            position,
+            phase,
+            green: plan.phases[phase].green.clone(),
+        })
+    }
+}
@@ -0,0 +1,71 @@
+use crate::city::cell::{Cell, Position};
+use crate::city::grid::CityGrid;
+use crate::routing::astar::{AStar, Router};
+use crate::routing::passability::PassabilityRules;
+use crate::simulation::simulation::WorldTime;
+use serde::{Deserialize, Serialize};
+
+/// a bus line, vehicles run through the stops in order and back on a fixed timetable
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransitLine {
+    pub name: String,
+    /// stops in order, on cells open to through traffic
+    pub stops: Vec<Position>,
+    /// ticks between two vehicles
+    pub headway: WorldTime,
+    /// passengers per vehicle
+    pub capacity: usize,
+    /// cells per tick
+    pub speed: f64,
+    /// ticks a vehicle waits at each stop
+    pub dwell: WorldTime,
+    /// vehicles continue from the last stop to the first instead of turning back
+    pub circular: bool,
+}
+
+impl Default for TransitLine {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            stops: Vec::new(),
+            headway: 20,
+            capacity: 20,
+            speed: 1.0,
+            dwell: 2,
+            circular: false,
+        }
+    }
+}
+
+impl TransitLine {
+    /// stops in the order vehicles serve them, repeated after the last
+    pub fn sequence(&self) -> Vec<Position> {
+        let mut sequence = self.stops.clone();
+        if !self.circular && self.stops.len() > 2 {
+            sequence.extend(self.stops[1..self.stops.len() - 1].iter().rev());
+        }
+        sequence
+    }
+
+    /// cells driven from each stop of `sequence` to the next, through cells only,
+    /// `Err` with the first pair of stops without a connection
+    pub fn route(
+        &self,
+        city: &CityGrid,
+        rules: &PassabilityRules,
+    ) -> Result<Vec<Vec<Position>>, (Position, Position)> {
+        let through = |_: &CityGrid, _: &Position, to: &Cell| {
+            (rules.is_through(to.cell_type) && to.is_open()).then_some(1)
+        };
+        let sequence = self.sequence();
+        (0..sequence.len())
+            .map(|i| {
+                let (from, to) = (sequence[i], sequence[(i + 1) % sequence.len()]);
+                AStar::default()
+                    .find_path(city, from, to, None, &through, None)
+                    .ok_or((from, to))
+            })
+            .collect()
+    }
+}
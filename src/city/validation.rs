@@ -0,0 +1,405 @@
+use crate::city::cell::{CellType, Direction, Position};
+use crate::city::config::CityConfig;
+use crate::city::grid::CityGrid;
+use crate::routing::analysis::{building_distances, component_labels, group_by_components};
+use crate::routing::passability::PassabilityRules;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// unreachable house/office or house/park pairs reported before the rest are summarized
+const MAX_UNREACHABLE_REPORTS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// a problem found by `CityConfig::validate`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind")]
+pub enum Issue {
+    UnknownCellType {
+        x: usize,
+        y: usize,
+        cell_type: String,
+    },
+    /// a `capacities` or `places` key that is not a cell type
+    UnknownCellTypeKey {
+        setting: String,
+        cell_type: String,
+    },
+    OutOfBounds {
+        x: usize,
+        y: usize,
+    },
+    /// the same coordinates appear more than once, the last entry wins
+    DuplicateCell {
+        x: usize,
+        y: usize,
+    },
+    /// agents cannot enter or leave a building without a road next to it
+    NotAdjacentToRoad {
+        position: Position,
+        cell_type: CellType,
+    },
+    /// agents cannot be placed without at least one cell of this type
+    MissingCellType {
+        cell_type: CellType,
+    },
+    Unreachable {
+        from: Position,
+        to: Position,
+    },
+    /// more unreachable pairs than reported individually
+    MoreUnreachable {
+        omitted: usize,
+    },
+    /// a trip between a house and a park without a path, agents assigned that park never arrive
+    UnreachablePark {
+        from: Position,
+        to: Position,
+    },
+    MoreUnreachableParks {
+        omitted: usize,
+    },
+    /// cells of a type have fewer places in total than agents live or work there
+    NotEnoughPlaces {
+        cell_type: CellType,
+        places: usize,
+        agents: usize,
+    },
+    /// fewer than two stops, or a headway, capacity or speed that is not positive
+    InvalidTransitLine {
+        line: String,
+    },
+    /// transit stops must be on cells open to through traffic
+    TransitStopNotOnRoad {
+        line: String,
+        position: Position,
+    },
+    /// vehicles cannot drive between two consecutive stops
+    TransitStopsUnconnected {
+        line: String,
+        from: Position,
+        to: Position,
+    },
+}
+
+impl Issue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Issue::DuplicateCell { .. }
+            | Issue::UnreachablePark { .. }
+            | Issue::MoreUnreachableParks { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::UnknownCellType { x, y, cell_type } => {
+                write!(f, "({}, {}): unknown cell type \"{}\"", x, y, cell_type)
+            }
+            Issue::UnknownCellTypeKey { setting, cell_type } => {
+                write!(f, "{}: unknown cell type \"{}\"", setting, cell_type)
+            }
+            Issue::OutOfBounds { x, y } => write!(f, "({}, {}): out of bounds", x, y),
+            Issue::DuplicateCell { x, y } => {
+                write!(f, "({}, {}): defined more than once, last one wins", x, y)
+            }
+            Issue::NotAdjacentToRoad {
+                position,
+                cell_type,
+            } => write!(
+                f,
+                "({}, {}): {:?} is not adjacent to a road",
+                position.x, position.y, cell_type
+            ),
+            Issue::MissingCellType { cell_type } => write!(f, "no {:?} on the map", cell_type),
+            Issue::Unreachable { from, to } => write!(
+                f,
+                "({}, {}) cannot reach ({}, {})",
+                from.x, from.y, to.x, to.y
+            ),
+            Issue::MoreUnreachable { omitted } => {
+                write!(f, "... and {} more unreachable pairs", omitted)
+            }
+            Issue::UnreachablePark { from, to } => write!(
+                f,
+                "({}, {}) cannot reach ({}, {}) (park trip)",
+                from.x, from.y, to.x, to.y
+            ),
+            Issue::MoreUnreachableParks { omitted } => {
+                write!(f, "... and {} more unreachable house/park pairs", omitted)
+            }
+            Issue::NotEnoughPlaces {
+                cell_type,
+                places,
+                agents,
+            } => write!(
+                f,
+                "{:?} cells have {} places for {} agents",
+                cell_type, places, agents
+            ),
+            Issue::InvalidTransitLine { line } => write!(
+                f,
+                "line {:?}: needs two stops and a positive headway, capacity and speed",
+                line
+            ),
+            Issue::TransitStopNotOnRoad { line, position } => write!(
+                f,
+                "line {:?}: stop ({}, {}) is not on a road",
+                line, position.x, position.y
+            ),
+            Issue::TransitStopsUnconnected { line, from, to } => write!(
+                f,
+                "line {:?}: no road from stop ({}, {}) to ({}, {})",
+                line, from.x, from.y, to.x, to.y
+            ),
+        }
+    }
+}
+
+/// which buildings reach each other
+struct Reachability<'a> {
+    city: &'a CityGrid,
+    rules: &'a PassabilityRules,
+    /// component labels if every move can be reversed, buildings then reach each other
+    /// exactly when they share a component of the road network
+    labels: Option<Vec<Option<usize>>>,
+}
+
+impl<'a> Reachability<'a> {
+    fn new(city: &'a CityGrid, rules: &'a PassabilityRules) -> Self {
+        let restricted = city
+            .cells()
+            .iter()
+            .any(|c| c.exits.is_some() || !c.banned_turns.is_empty());
+        Self {
+            city,
+            rules,
+            labels: (!restricted).then(|| component_labels(city, rules)),
+        }
+    }
+
+    /// pairs of a start and a target without a path there or back, each once, the first
+    /// `MAX_UNREACHABLE_REPORTS` of them and how many there are in total
+    fn unreachable(
+        &self,
+        starts: &[Position],
+        targets: &[Position],
+    ) -> (Vec<(Position, Position)>, usize) {
+        let mut listed = Vec::new();
+        let mut total = 0;
+        let mut report = |from: &Position, to: &[Position]| {
+            total += to.len();
+            for to in to.iter().take(MAX_UNREACHABLE_REPORTS - listed.len()) {
+                listed.push((*from, *to));
+            }
+        };
+        match &self.labels {
+            None => {
+                // one-way roads can block a trip in one direction only
+                let returns = targets
+                    .iter()
+                    .map(|to| building_distances(self.city, self.rules, *to))
+                    .collect::<Vec<_>>();
+                for from in starts {
+                    let reached = building_distances(self.city, self.rules, *from);
+                    let missed = targets
+                        .iter()
+                        .zip(&returns)
+                        .filter(|(to, back)| !reached.contains_key(to) || !back.contains_key(from))
+                        .map(|(to, _)| *to)
+                        .collect::<Vec<_>>();
+                    report(from, &missed);
+                }
+            }
+            Some(labels) => {
+                // components connect both ways, each pair is checked once
+                let start_groups = group_by_components(self.city, labels, starts);
+                let target_groups = group_by_components(self.city, labels, targets);
+                for (from_labels, from_cells) in &start_groups {
+                    for (to_labels, to_cells) in &target_groups {
+                        if from_labels.iter().any(|l| to_labels.contains(l)) {
+                            continue;
+                        }
+                        for from in from_cells {
+                            report(from, to_cells);
+                        }
+                    }
+                }
+            }
+        }
+        (listed, total)
+    }
+}
+
+impl CityConfig {
+    /// unknown cell types, cells out of bounds and duplicate cells, the errors
+    /// among them keep `to_city_grid` from building the grid
+    pub fn validate_cells(&self) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        let mut seen = HashSet::new();
+        for cell in &self.cells {
+            if CellType::from_str(&cell.cell_type).is_err() {
+                issues.push(Issue::UnknownCellType {
+                    x: cell.x,
+                    y: cell.y,
+                    cell_type: cell.cell_type.clone(),
+                });
+            }
+            if cell.x >= self.width || cell.y >= self.height {
+                issues.push(Issue::OutOfBounds {
+                    x: cell.x,
+                    y: cell.y,
+                });
+            } else if !seen.insert((cell.x, cell.y)) {
+                issues.push(Issue::DuplicateCell {
+                    x: cell.x,
+                    y: cell.y,
+                });
+            }
+        }
+        for (setting, keys) in [
+            ("capacities", self.capacities.keys().collect::<Vec<_>>()),
+            ("places", self.places.keys().collect()),
+        ] {
+            for key in keys {
+                if CellType::from_str(key).is_err() {
+                    issues.push(Issue::UnknownCellTypeKey {
+                        setting: setting.to_string(),
+                        cell_type: key.clone(),
+                    });
+                }
+            }
+        }
+        issues
+    }
+
+    /// check the config for mistakes that `to_city_grid` would silently accept
+    pub fn validate(&self, rules: &PassabilityRules) -> Vec<Issue> {
+        let mut issues = self.validate_cells();
+        // the checks below need the grid, which cannot be built with those errors
+        let Ok(city) = self.to_city_grid() else {
+            return issues;
+        };
+
+        let houses = city.find_cells_of_type(CellType::House);
+        let offices = city.find_cells_of_type(CellType::Office);
+        for (cell_type, cells) in [(CellType::House, &houses), (CellType::Office, &offices)] {
+            if cells.is_empty() {
+                issues.push(Issue::MissingCellType { cell_type });
+            }
+            for position in cells {
+                let has_road = Direction::ALL.into_iter().any(|d| {
+                    city.step(position, d)
+                        .and_then(|p| city.get_cell(&p))
+                        .is_some_and(|c| rules.is_through(c.cell_type))
+                });
+                if !has_road {
+                    issues.push(Issue::NotAdjacentToRoad {
+                        position: *position,
+                        cell_type,
+                    });
+                }
+            }
+        }
+
+        let parks = city.find_cells_of_type(CellType::Park);
+        let reachability = Reachability::new(&city, rules);
+        let (listed, total) = reachability.unreachable(&houses, &offices);
+        issues.extend(
+            listed
+                .iter()
+                .map(|&(from, to)| Issue::Unreachable { from, to }),
+        );
+        if total > listed.len() {
+            issues.push(Issue::MoreUnreachable {
+                omitted: total - listed.len(),
+            });
+        }
+        let (listed, total) = reachability.unreachable(&houses, &parks);
+        issues.extend(
+            listed
+                .iter()
+                .map(|&(from, to)| Issue::UnreachablePark { from, to }),
+        );
+        if total > listed.len() {
+            issues.push(Issue::MoreUnreachableParks {
+                omitted: total - listed.len(),
+            });
+        }
+
+        for line in &city.lines {
+            if line.stops.len() < 2 || line.headway <= 0 || line.capacity == 0 || line.speed <= 0.0
+            {
+                issues.push(Issue::InvalidTransitLine {
+                    line: line.name.clone(),
+                });
+                continue;
+            }
+            let off_road = line
+                .stops
+                .iter()
+                .filter(|p| {
+                    !city
+                        .get_cell(p)
+                        .is_some_and(|c| rules.is_through(c.cell_type))
+                })
+                .map(|p| Issue::TransitStopNotOnRoad {
+                    line: line.name.clone(),
+                    position: *p,
+                })
+                .collect::<Vec<_>>();
+            if !off_road.is_empty() {
+                issues.extend(off_road);
+            } else if let Err((from, to)) = line.route(&city, rules) {
+                issues.push(Issue::TransitStopsUnconnected {
+                    line: line.name.clone(),
+                    from,
+                    to,
+                });
+            }
+        }
+
+        issues
+    }
+
+    /// check that every cell type has room for the agents that live or work
+    /// there, `demand` as in `SimulationConfig::place_demand`
+    pub fn validate_places(&self, demand: &[(CellType, usize)]) -> Vec<Issue> {
+        let Ok(city) = self.to_city_grid() else {
+            return Vec::new(); // reported by `validate`
+        };
+        demand
+            .iter()
+            .filter_map(|&(cell_type, agents)| {
+                let cells = city.find_cells_of_type(cell_type);
+                if cells.is_empty() {
+                    // houses and offices are required by `validate`, other workplaces only by an archetype
+                    let reported = matches!(cell_type, CellType::House | CellType::Office);
+                    return (agents > 0 && !reported)
+                        .then_some(Issue::MissingCellType { cell_type });
+                }
+                let mut places = 0;
+                for position in cells {
+                    match city.get_cell(&position).and_then(|cell| cell.places) {
+                        Some(n) => places += n,
+                        None => return None, // unlimited
+                    }
+                }
+                (places < agents).then_some(Issue::NotEnoughPlaces {
+                    cell_type,
+                    places,
+                    agents,
+                })
+            })
+            .collect()
+    }
+}
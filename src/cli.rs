@@ -0,0 +1,160 @@
+use crate::city::generator::RoadPattern;
+use crate::simulation::batch::OutputFormat;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// city map, JSON or a `.txt`/`.map` text map, the built-in 10x10 map is used if omitted
+    #[arg(long, global = true)]
+    pub city: Option<PathBuf>,
+
+    /// simulation config JSON file, defaults are used if omitted
+    #[arg(long, global = true)]
+    pub sim_config: Option<PathBuf>,
+
+    /// override the number of agents
+    #[arg(long, global = true)]
+    pub agents: Option<usize>,
+
+    /// override the RNG seed
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// run the simulation behind the visualization server (default)
+    Serve(ServeArgs),
+    /// run a fixed number of ticks headless and write every update to a file
+    Run(RunArgs),
+    /// load the city and simulation config and report problems
+    Validate,
+    /// generate a city from parameters and write it (JSON, or a text map for `.txt`/`.map`)
+    Generate(GenerateArgs),
+    /// write the effective city (JSON, or a text map for `.txt`/`.map`) or simulation config
+    Export(ExportArgs),
+    /// time ticks on a generated city, 100000 agents unless `--agents` is given
+    Bench(BenchArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ServeArgs {
+    /// address the visualization server listens on
+    #[arg(long, default_value = "127.0.0.1:8000")]
+    pub bind: SocketAddr,
+
+    /// directory with the built frontend assets
+    #[arg(long, default_value = "frontend/dist")]
+    pub static_dir: PathBuf,
+}
+
+impl Default for ServeArgs {
+    fn default() -> Self {
+        Self {
+            bind: "127.0.0.1:8000".parse().expect("invalid endpoint string"),
+            static_dir: PathBuf::from("frontend/dist"),
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct RunArgs {
+    /// number of ticks to simulate
+    #[arg(long)]
+    pub ticks: u64,
+
+    /// output file, one record per tick
+    #[arg(long, short)]
+    pub output: PathBuf,
+
+    /// output format, inferred from the output file extension if omitted
+    #[arg(long)]
+    pub format: Option<OutputFormat>,
+
+    /// also write per-agent updates (CSV: to `<output>.agents.csv`)
+    #[arg(long)]
+    pub with_agents: bool,
+
+    /// also write every completed trip to `<output>.trips.jsonl` (CSV: `<output>.trips.csv`)
+    #[arg(long)]
+    pub with_trips: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ExportArgs {
+    /// what to export
+    #[arg(value_enum, default_value_t = ExportTarget::City)]
+    pub target: ExportTarget,
+
+    /// output file, stdout if omitted
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportTarget {
+    City,
+    Simulation,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct GenerateArgs {
+    #[arg(long, default_value_t = 50)]
+    pub width: usize,
+
+    #[arg(long, default_value_t = 50)]
+    pub height: usize,
+
+    #[arg(long, value_enum, default_value_t = RoadPattern::Grid)]
+    pub pattern: RoadPattern,
+
+    /// cells between parallel roads
+    #[arg(long, default_value_t = 4)]
+    pub road_spacing: usize,
+
+    /// share of road-side lots zoned as houses
+    #[arg(long, default_value_t = 0.5)]
+    pub houses: f64,
+
+    /// share of road-side lots zoned as offices
+    #[arg(long, default_value_t = 0.25)]
+    pub offices: f64,
+
+    /// share of lots zoned as parks
+    #[arg(long, default_value_t = 0.1)]
+    pub parks: f64,
+
+    /// share of road-side lots left empty
+    #[arg(long, default_value_t = 0.15)]
+    pub empty: f64,
+
+    /// energy stations per 1000 cells
+    #[arg(long, default_value_t = 2.0)]
+    pub energy_stations: f64,
+
+    /// output file, stdout (JSON) if omitted
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct BenchArgs {
+    #[arg(long, default_value_t = 1000)]
+    pub width: usize,
+
+    #[arg(long, default_value_t = 1000)]
+    pub height: usize,
+
+    #[arg(long, value_enum, default_value_t = RoadPattern::Grid)]
+    pub pattern: RoadPattern,
+
+    /// number of ticks to time
+    #[arg(long, default_value_t = 100)]
+    pub ticks: u64,
+}
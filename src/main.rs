@@ -1,24 +1,314 @@
 mod agent;
 mod city;
+mod cli;
+mod routing;
 mod simulation;
 mod visualization;
 
-use city::config::CityConfig;
+use city::cell::CellType;
+use city::config::{CityConfig, ConfigError};
+use city::generator::{self, GeneratorConfig, Zoning};
+use city::grid::CityGrid;
+use city::validation::Severity;
+use clap::Parser;
+use cli::{BenchArgs, Cli, Command, ExportArgs, ExportTarget, GenerateArgs, RunArgs, ServeArgs};
+use routing::analysis;
+use routing::passability::PassabilityRules;
+use simulation::batch::{run_batch, BatchOptions, OutputFormat, UpdateWriter};
 use simulation::config::SimulationConfig;
 use simulation::simulation::Simulation;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
+
+/// agents simulated by `bench` unless `--agents` is given
+const BENCH_AGENTS: usize = 100_000;
+
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
     env_logger::init();
-    let city_config = CityConfig::default();
-    log::info!("city config: {}", serde_json::to_string(&city_config)?);
-    let city = city_config.to_city_grid();
+    let cli = Cli::parse();
+    let sim_config = load_sim_config(&cli)?;
+    match cli.command {
+        Some(Command::Generate(args)) => {
+            generate(&args, sim_config.seed, &sim_config.routing.passability)
+        }
+        Some(Command::Bench(args)) => {
+            let num_agents = cli.agents.unwrap_or(BENCH_AGENTS);
+            bench(
+                &args,
+                SimulationConfig {
+                    num_agents,
+                    ..sim_config
+                },
+            )
+        }
+        Some(Command::Validate) => validate(&load_city_config(cli.city.as_deref())?, &sim_config),
+        Some(Command::Export(args)) => {
+            export(&load_city_config(cli.city.as_deref())?, &sim_config, args)
+        }
+        Some(Command::Run(args)) => {
+            let city_config = load_valid_city_config(cli.city.as_deref(), &sim_config)?;
+            run(city_config, sim_config, args)
+        }
+        Some(Command::Serve(args)) => {
+            let city_config = load_valid_city_config(cli.city.as_deref(), &sim_config)?;
+            serve(city_config, sim_config, args).await
+        }
+        None => {
+            let city_config = load_valid_city_config(cli.city.as_deref(), &sim_config)?;
+            serve(city_config, sim_config, ServeArgs::default()).await
+        }
+    }
+}
+
+/// the city config, with warnings logged and errors refusing to run a simulation on it
+fn load_valid_city_config(
+    path: Option<&Path>,
+    sim_config: &SimulationConfig,
+) -> Result<CityConfig, anyhow::Error> {
+    let city_config = load_city_config(path)?;
+    let mut issues = city_config.validate(&sim_config.routing.passability);
+    issues.extend(city_config.validate_places(&sim_config.place_demand()));
+    for issue in issues.iter().filter(|i| i.severity() == Severity::Warning) {
+        log::warn!("city config: {}", issue);
+    }
+    if issues.iter().any(|i| i.severity() == Severity::Error) {
+        return Err(ConfigError::Invalid(issues).into());
+    }
+    Ok(city_config)
+}
+
+fn load_city_config(path: Option<&Path>) -> Result<CityConfig, anyhow::Error> {
+    match path {
+        Some(path) => {
+            log::info!("loading city from {}", path.display());
+            Ok(CityConfig::load(path)?)
+        }
+        None => Ok(CityConfig::default()),
+    }
+}
+
+fn load_sim_config(cli: &Cli) -> Result<SimulationConfig, anyhow::Error> {
+    let mut config = match &cli.sim_config {
+        Some(path) => {
+            log::info!("loading simulation config from {}", path.display());
+            SimulationConfig::from_file(path).map_err(anyhow::Error::msg)?
+        }
+        None => SimulationConfig::default(),
+    };
+    if let Some(num_agents) = cli.agents {
+        config.num_agents = num_agents;
+    }
+    if let Some(seed) = cli.seed {
+        config.seed = seed;
+    }
+    Ok(config)
+}
+
+fn build_simulation(
+    city_config: &CityConfig,
+    sim_config: SimulationConfig,
+) -> Result<Simulation, ConfigError> {
+    let city = city_config.to_city_grid()?;
     log::info!("initial city grid:\n{}", city);
-    let sim_config = SimulationConfig::default();
     let mut sim = Simulation::new(city, sim_config);
-    sim.initialize();
-    let addr = "127.0.0.1:8000".parse().expect("invalid endpoint string");
-    log::info!("starting visualization server at http://{}", addr);
-    visualization::start_server(Arc::new(Mutex::new(sim)), addr).await
+    sim.initialize()
+        .map_err(|issue| ConfigError::Invalid(vec![issue]))?;
+    Ok(sim)
+}
+
+async fn serve(
+    city_config: CityConfig,
+    sim_config: SimulationConfig,
+    args: ServeArgs,
+) -> Result<(), anyhow::Error> {
+    log::info!("city config: {}", serde_json::to_string(&city_config)?);
+    let sim = build_simulation(&city_config, sim_config)?;
+    log::info!("starting visualization server at http://{}", args.bind);
+    visualization::start_server(Arc::new(Mutex::new(sim)), args.bind, args.static_dir).await
+}
+
+fn run(
+    city_config: CityConfig,
+    sim_config: SimulationConfig,
+    args: RunArgs,
+) -> Result<(), anyhow::Error> {
+    let mut sim = build_simulation(&city_config, sim_config)?;
+    let format = match args.format {
+        Some(format) => format,
+        None => match args.output.extension().and_then(|e| e.to_str()) {
+            Some(ext) => ext.parse::<OutputFormat>().map_err(anyhow::Error::msg)?,
+            None => OutputFormat::Jsonl,
+        },
+    };
+    let options = BatchOptions {
+        ticks: args.ticks,
+        format,
+        include_agents: args.with_agents,
+        include_trips: args.with_trips,
+    };
+    let agents_out = if options.include_agents && format == OutputFormat::Csv {
+        Some(BufWriter::new(File::create(
+            args.output.with_extension("agents.csv"),
+        )?))
+    } else {
+        None
+    };
+    let trips_out = if options.include_trips {
+        Some(BufWriter::new(File::create(
+            args.output.with_extension(format!("trips.{}", format)),
+        )?))
+    } else {
+        None
+    };
+    let out = BufWriter::new(File::create(&args.output)?);
+    let mut writer = UpdateWriter::new(&options, out, agents_out, trips_out);
+    run_batch(&mut sim, &options, &mut writer)
+}
+
+fn validate(city_config: &CityConfig, sim_config: &SimulationConfig) -> Result<(), anyhow::Error> {
+    // without a grid only the issues that prevent building it are reported
+    if let Ok(city) = city_config.to_city_grid() {
+        describe_city(&city, sim_config);
+    }
+    let mut issues = city_config.validate(&sim_config.routing.passability);
+    issues.extend(city_config.validate_places(&sim_config.place_demand()));
+    for issue in &issues {
+        println!("{:?}: {}", issue.severity(), issue);
+    }
+    let errors = issues
+        .iter()
+        .filter(|i| i.severity() == Severity::Error)
+        .count();
+    if errors > 0 {
+        anyhow::bail!("{} errors, {} warnings", errors, issues.len() - errors);
+    }
+    println!("ok, {} warnings", issues.len());
+    Ok(())
+}
+
+fn describe_city(city: &CityGrid, sim_config: &SimulationConfig) {
+    println!("{}", city);
+    println!(
+        "{}x{} city, {} houses, {} offices, {} agents",
+        city.width,
+        city.height,
+        city.find_cells_of_type(CellType::House).len(),
+        city.find_cells_of_type(CellType::Office).len(),
+        sim_config.num_agents
+    );
+    let population = sim_config
+        .archetypes
+        .iter()
+        .zip(sim_config.population())
+        .map(|(archetype, count)| format!("{} {}", count, archetype.name))
+        .collect::<Vec<_>>();
+    println!("population: {}", population.join(", "));
+    let analysis = analysis::analyze(city, &sim_config.routing.passability);
+    println!(
+        "{} road networks, {} of {} house to office and {} of {} house to park trips unreachable",
+        analysis.components.len(),
+        analysis.house_office.unreachable,
+        analysis.house_office.pairs,
+        analysis.house_park.unreachable,
+        analysis.house_park.pairs
+    );
+}
+
+fn generate(args: &GenerateArgs, seed: u64, rules: &PassabilityRules) -> Result<(), anyhow::Error> {
+    let config = GeneratorConfig {
+        width: args.width,
+        height: args.height,
+        seed,
+        pattern: args.pattern,
+        road_spacing: args.road_spacing,
+        zoning: Zoning {
+            house: args.houses,
+            office: args.offices,
+            park: args.parks,
+            empty: args.empty,
+        },
+        energy_station_density: args.energy_stations,
+    };
+    let city_config = generator::generate(&config);
+    for issue in city_config.validate(rules) {
+        log::warn!("generated city: {:?}: {}", issue.severity(), issue);
+    }
+    match &args.output {
+        Some(path) => city_config.save(path)?,
+        None => println!("{}", serde_json::to_string_pretty(&city_config)?),
+    }
+    Ok(())
+}
+
+fn bench(args: &BenchArgs, sim_config: SimulationConfig) -> Result<(), anyhow::Error> {
+    let started = Instant::now();
+    let city = generator::generate(&GeneratorConfig {
+        width: args.width,
+        height: args.height,
+        seed: sim_config.seed,
+        pattern: args.pattern,
+        ..GeneratorConfig::default()
+    })
+    .to_city_grid()?;
+    println!(
+        "{}x{} city, {} roads, {} houses, {} offices: {:?}",
+        city.width,
+        city.height,
+        city.find_cells_of_type(CellType::Road).len(),
+        city.find_cells_of_type(CellType::House).len(),
+        city.find_cells_of_type(CellType::Office).len(),
+        started.elapsed()
+    );
+
+    let started = Instant::now();
+    let num_agents = sim_config.num_agents;
+    let mut sim = Simulation::new(city, sim_config);
+    sim.initialize()
+        .map_err(|issue| ConfigError::Invalid(vec![issue]))?;
+    println!("{} agents: {:?}", num_agents, started.elapsed());
+
+    let mut ticks = Vec::new();
+    for _ in 0..args.ticks {
+        let started = Instant::now();
+        sim.do_tick();
+        ticks.push(started.elapsed());
+    }
+    if ticks.is_empty() {
+        return Ok(());
+    }
+    ticks.sort();
+    let total: Duration = ticks.iter().sum();
+    let median = ticks[ticks.len() / 2];
+    println!(
+        "{} ticks: median {:?} ({:.1} ticks/s), average {:?}, slowest {:?}",
+        ticks.len(),
+        median,
+        1.0 / median.as_secs_f64(),
+        total / ticks.len() as u32,
+        ticks[ticks.len() - 1]
+    );
+    Ok(())
+}
+
+fn export(
+    city_config: &CityConfig,
+    sim_config: &SimulationConfig,
+    args: ExportArgs,
+) -> Result<(), anyhow::Error> {
+    match (args.target, args.output) {
+        (ExportTarget::City, Some(path)) => city_config.save(path).map_err(anyhow::Error::msg)?,
+        (ExportTarget::City, None) => println!("{}", serde_json::to_string_pretty(city_config)?),
+        (ExportTarget::Simulation, Some(path)) => {
+            serde_json::to_writer_pretty(File::create(path)?, sim_config)?
+        }
+        (ExportTarget::Simulation, None) => {
+            println!("{}", serde_json::to_string_pretty(sim_config)?)
+        }
+    }
+    Ok(())
 }
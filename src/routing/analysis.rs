@@ -0,0 +1,255 @@
+use crate::city::cell::{Cell, CellType, Direction, Position};
+use crate::city::grid::CityGrid;
+use crate::routing::passability::PassabilityRules;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+/// connectivity of the through traffic network and the trips it allows,
+/// distances of single trips come from `trip_distances` and `pair_distance`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkAnalysis {
+    /// largest first
+    pub components: Vec<Component>,
+    /// pairs of buildings sharing no component, one-way roads and turn restrictions are ignored
+    pub house_office: PairCount,
+    pub house_park: PairCount,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PairCount {
+    pub pairs: usize,
+    pub unreachable: usize,
+}
+
+/// through cells connected to each other, ignoring one-way restrictions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Component {
+    pub origin: Position, // first cell in row order
+    pub cells: usize,
+    /// buildings adjacent to the component
+    pub houses: usize,
+    pub offices: usize,
+    pub parks: usize,
+    pub energy_stations: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TripDistance {
+    pub from: Position,
+    pub to: Position,
+    pub to_type: CellType,
+    pub distance: Option<usize>, // steps on the shortest legal path
+}
+
+pub fn analyze(city: &CityGrid, rules: &PassabilityRules) -> NetworkAnalysis {
+    let labels = component_labels(city, rules);
+    let groups =
+        |cell_type| group_by_components(city, &labels, &city.find_cells_of_type(cell_type));
+    let houses = groups(CellType::House);
+    let count = |targets: &BTreeMap<Vec<usize>, Vec<Position>>| {
+        let mut count = PairCount::default();
+        for (from_labels, from_cells) in &houses {
+            for (to_labels, to_cells) in targets {
+                let pairs = from_cells.len() * to_cells.len();
+                count.pairs += pairs;
+                if !from_labels.iter().any(|l| to_labels.contains(l)) {
+                    count.unreachable += pairs;
+                }
+            }
+        }
+        count
+    };
+    let house_office = count(&groups(CellType::Office));
+    let house_park = count(&groups(CellType::Park));
+
+    let mut components = components(city, rules, &labels);
+    components.sort_by_key(|c| Reverse(c.cells));
+    NetworkAnalysis {
+        components,
+        house_office,
+        house_park,
+    }
+}
+
+/// shortest legal distances from the building at `from` to every office and park
+pub fn trip_distances(
+    city: &CityGrid,
+    rules: &PassabilityRules,
+    from: Position,
+) -> Vec<TripDistance> {
+    let distances = building_distances(city, rules, from);
+    [CellType::Office, CellType::Park]
+        .into_iter()
+        .flat_map(|to_type| {
+            city.find_cells_of_type(to_type)
+                .into_iter()
+                .map(move |to| (to, to_type))
+        })
+        .map(|(to, to_type)| TripDistance {
+            from,
+            to,
+            to_type,
+            distance: distances.get(&to).copied(),
+        })
+        .collect()
+}
+
+/// shortest legal distance from the building at `from` to the one at `to`
+pub fn pair_distance(
+    city: &CityGrid,
+    rules: &PassabilityRules,
+    from: Position,
+    to: Position,
+) -> TripDistance {
+    TripDistance {
+        from,
+        to,
+        to_type: city
+            .get_cell(&to)
+            .map_or(CellType::Empty, |cell| cell.cell_type),
+        distance: building_distances(city, rules, from).get(&to).copied(),
+    }
+}
+
+/// steps to every building a trip starting at `from` can end at, honoring
+/// passability, closed cells, one-way roads and turn restrictions
+pub fn building_distances(
+    city: &CityGrid,
+    rules: &PassabilityRules,
+    from: Position,
+) -> HashMap<Position, usize> {
+    let mut reached = HashMap::new();
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+    queue.push_back((from, None, 0));
+    visited.insert((from, None));
+
+    while let Some((current, heading, steps)) = queue.pop_front() {
+        let Some(current_cell) = city.get_cell(&current) else {
+            continue;
+        };
+        let from_through = is_through(rules, current_cell);
+        for (direction, next) in city.moves(&current, heading) {
+            let Some(cell) = city.get_cell(&next) else {
+                continue;
+            };
+            if is_through(rules, cell) {
+                let heading = city.effective_heading(&next, Some(direction));
+                if visited.insert((next, heading)) {
+                    queue.push_back((next, heading, steps + 1));
+                }
+            } else if from_through && cell.is_open() {
+                // breadth first, the first visit is the shortest
+                reached.entry(next).or_insert(steps + 1);
+            }
+        }
+    }
+    reached
+}
+
+/// component number of every open through cell, indexed like `CityGrid::cells`,
+/// components are numbered in row order of their first cell
+pub fn component_labels(city: &CityGrid, rules: &PassabilityRules) -> Vec<Option<usize>> {
+    let mut labels = vec![None; city.cells().len()];
+    let mut count = 0;
+    for (i, cell) in city.cells().iter().enumerate() {
+        if !is_through(rules, cell) || labels[i].is_some() {
+            continue;
+        }
+        labels[i] = Some(count);
+        let mut queue = VecDeque::from([cell.position]);
+        while let Some(current) = queue.pop_front() {
+            for next in Direction::ALL
+                .into_iter()
+                .filter_map(|d| city.step(&current, d))
+            {
+                let Some(j) = city.index(&next) else {
+                    continue;
+                };
+                if labels[j].is_none() && is_through(rules, &city.cells()[j]) {
+                    labels[j] = Some(count);
+                    queue.push_back(next);
+                }
+            }
+        }
+        count += 1;
+    }
+    labels
+}
+
+/// components a building at `position` can be entered from, sorted
+pub fn adjacent_components(
+    city: &CityGrid,
+    labels: &[Option<usize>],
+    position: &Position,
+) -> Vec<usize> {
+    let mut adjacent = Direction::ALL
+        .into_iter()
+        .filter_map(|d| labels[city.index(&city.step(position, d)?)?])
+        .collect::<Vec<_>>();
+    adjacent.sort_unstable();
+    adjacent.dedup();
+    adjacent
+}
+
+/// `cells` by the components they can be entered from, buildings in one group reach
+/// a building of another exactly when the groups share a component, unless roads are
+/// restricted to one way or turns
+pub fn group_by_components(
+    city: &CityGrid,
+    labels: &[Option<usize>],
+    cells: &[Position],
+) -> BTreeMap<Vec<usize>, Vec<Position>> {
+    let mut groups: BTreeMap<Vec<usize>, Vec<Position>> = BTreeMap::new();
+    for position in cells {
+        let adjacent = adjacent_components(city, labels, position);
+        groups.entry(adjacent).or_default().push(*position);
+    }
+    groups
+}
+
+/// whether through traffic can pass `cell`, closed cells block it
+fn is_through(rules: &PassabilityRules, cell: &Cell) -> bool {
+    rules.is_through(cell.cell_type) && cell.is_open()
+}
+
+fn components(
+    city: &CityGrid,
+    rules: &PassabilityRules,
+    labels: &[Option<usize>],
+) -> Vec<Component> {
+    let mut components: Vec<Component> = Vec::new();
+    for (cell, label) in city.cells().iter().zip(labels) {
+        let Some(label) = *label else {
+            continue;
+        };
+        if label == components.len() {
+            components.push(Component {
+                origin: cell.position,
+                cells: 0,
+                houses: 0,
+                offices: 0,
+                parks: 0,
+                energy_stations: 0,
+            });
+        }
+        components[label].cells += 1;
+    }
+    for cell in city.cells() {
+        if rules.is_through(cell.cell_type) || !cell.is_open() {
+            continue;
+        }
+        for label in adjacent_components(city, labels, &cell.position) {
+            let component = &mut components[label];
+            match cell.cell_type {
+                CellType::House => component.houses += 1,
+                CellType::Office => component.offices += 1,
+                CellType::Park => component.parks += 1,
+                CellType::EnergyStation => component.energy_stations += 1,
+                CellType::Road | CellType::Empty => {}
+            }
+        }
+    }
+    components
+}
@@ -0,0 +1,145 @@
+use crate::city::cell::{Direction, Position};
+use crate::city::grid::CityGrid;
+use crate::routing::cost::CellCost;
+use rustc_hash::FxHashMap;
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::BinaryHeap;
+use std::fmt::Debug;
+
+/// finds paths on the city grid
+pub trait Router: Debug + Send + Sync {
+    /// path from `from` (exclusive) to `to` (inclusive), `None` if unreachable
+    /// or every path costs more than `max_cost`, `heading` is the direction
+    /// `from` was entered with
+    fn find_path(
+        &self,
+        city: &CityGrid,
+        from: Position,
+        to: Position,
+        heading: Option<Direction>,
+        cost: &dyn CellCost,
+        max_cost: Option<u64>,
+    ) -> Option<Vec<Position>>;
+}
+
+/// A* search with Manhattan distance heuristic
+///
+/// nodes are (position, heading) pairs so turn restrictions are honored exactly
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AStar {
+    /// give up after expanding this many nodes, unlimited if `None`
+    pub max_expansions: Option<usize>,
+}
+
+/// a position together with the direction it was entered with
+type Node = (Position, Option<Direction>);
+
+impl Router for AStar {
+    fn find_path(
+        &self,
+        city: &CityGrid,
+        from: Position,
+        to: Position,
+        heading: Option<Direction>,
+        cost: &dyn CellCost,
+        max_cost: Option<u64>,
+    ) -> Option<Vec<Position>> {
+        if from == to {
+            return Some(Vec::new());
+        }
+        let goal = city.get_cell(&to)?;
+
+        // every path ends with a step from a neighbor that may enter the goal,
+        // aiming at those instead of the goal keeps the estimate tight when
+        // the goal can only be entered from one side
+        let entries = Direction::ALL
+            .into_iter()
+            .filter_map(|d| city.step(&to, d))
+            .filter(|p| cost.cost(city, p, goal).is_some())
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return None;
+        }
+        let heuristic = |p: &Position| {
+            let steps = if *p == to {
+                0
+            } else {
+                entries.iter().map(|e| p.distance(e)).min().unwrap_or(0) + 1
+            };
+            steps as u64 * cost.min_cost() as u64
+        };
+        // (f, h, y, x, heading) keeps the pop order deterministic on ties
+        let mut open = BinaryHeap::new();
+        // best known cost of each node and the node it was reached from
+        let mut scores: FxHashMap<Node, (u64, Node)> = FxHashMap::default();
+
+        let heading = city.effective_heading(&from, heading);
+        let start = (from, heading);
+        scores.insert(start, (0, start));
+        let h = heuristic(&from);
+        open.push(Reverse((h, h, from.y, from.x, heading)));
+
+        let mut expansions = 0;
+        while let Some(Reverse((f, h, y, x, heading))) = open.pop() {
+            let current = (Position::new(x, y), heading);
+            let g = scores[&current].0;
+            if f > g + h {
+                continue; // stale entry, a cheaper route was found later
+            }
+            if max_cost.is_some_and(|max| f > max) {
+                break; // entries pop in f order, nothing cheaper is left
+            }
+            if current.0 == to {
+                return Some(reconstruct_path(&scores, start, current));
+            }
+            expansions += 1;
+            if self.max_expansions.is_some_and(|max| expansions > max) {
+                break;
+            }
+
+            for (direction, next) in city.moves(&current.0, heading) {
+                let Some(cell) = city.get_cell(&next) else {
+                    continue;
+                };
+                let Some(step) = cost.cost(city, &current.0, cell) else {
+                    continue;
+                };
+                let heading = city.effective_heading(&next, Some(direction));
+                let node = (next, heading);
+                let tentative = g + step as u64;
+                let improved = match scores.entry(node) {
+                    Entry::Occupied(mut e) if tentative < e.get().0 => {
+                        e.insert((tentative, current));
+                        true
+                    }
+                    Entry::Occupied(_) => false,
+                    Entry::Vacant(e) => {
+                        e.insert((tentative, current));
+                        true
+                    }
+                };
+                if improved {
+                    let h = heuristic(&next);
+                    open.push(Reverse((tentative + h, h, next.y, next.x, heading)));
+                }
+            }
+        }
+        None
+    }
+}
+
+fn reconstruct_path(
+    scores: &FxHashMap<Node, (u64, Node)>,
+    start: Node,
+    goal: Node,
+) -> Vec<Position> {
+    let mut path = Vec::new();
+    let mut current = goal;
+    while current != start {
+        path.push(current.0);
+        current = scores[&current].1;
+    }
+    path.reverse();
+    path
+}
@@ -0,0 +1,85 @@
+use crate::city::cell::{Cell, Direction, Position};
+use crate::city::grid::CityGrid;
+
+/// per-cell cost function used by routers, shared by agents planning in parallel
+pub trait CellCost: Sync {
+    /// cost of moving from `from` into the cell `to`, `None` if the move is not allowed
+    fn cost(&self, city: &CityGrid, from: &Position, to: &Cell) -> Option<u32>;
+
+    /// lower bound of `cost` for any allowed move, scales the A* heuristic
+    fn min_cost(&self) -> u32 {
+        1
+    }
+}
+
+impl<F> CellCost for F
+where
+    F: Fn(&CityGrid, &Position, &Cell) -> Option<u32> + Sync,
+{
+    fn cost(&self, city: &CityGrid, from: &Position, to: &Cell) -> Option<u32> {
+        self(city, from, to)
+    }
+}
+
+/// every non-empty cell costs one step
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UniformCost;
+
+impl CellCost for UniformCost {
+    fn cost(&self, _city: &CityGrid, _from: &Position, to: &Cell) -> Option<u32> {
+        to.is_open().then_some(1)
+    }
+}
+
+/// cost of a tick spent crossing a free cell under `CongestionCost`
+pub const BASE_STEP_COST: u32 = 10;
+
+/// cells cost the ticks an agent of one mode needs to cross them, the slower of
+/// the mode speed and the speed limit, and more the more occupants were observed
+/// per lane, so fast modes prefer arterials and agents spread over alternative routes
+#[derive(Debug, Clone, Copy)]
+pub struct CongestionCost {
+    /// extra steps per occupant, 0 disables congestion awareness
+    pub weight: f64,
+    /// cells per tick of the mode planning
+    pub speed: f64,
+}
+
+impl CellCost for CongestionCost {
+    fn cost(&self, city: &CityGrid, from: &Position, to: &Cell) -> Option<u32> {
+        UniformCost.cost(city, from, to)?;
+        let base = BASE_STEP_COST as f64 * to.travel_time(self.speed);
+        let penalty =
+            self.weight * to.observed_occupancy / to.lanes.max(1) as f64 * BASE_STEP_COST as f64;
+        Some((base + penalty).round() as u32)
+    }
+
+    fn min_cost(&self) -> u32 {
+        // no cell is crossed faster than at the mode speed
+        ((BASE_STEP_COST as f64 / self.speed).floor() as u32).max(1)
+    }
+}
+
+/// total cost of walking `path` starting at `from` entered with `heading`,
+/// `None` if any step is not allowed
+pub fn path_cost(
+    city: &CityGrid,
+    from: &Position,
+    path: &[Position],
+    mut heading: Option<Direction>,
+    cost: &dyn CellCost,
+) -> Option<u64> {
+    let mut total = 0;
+    let mut current = *from;
+    for next in path {
+        let direction = Direction::between(&current, next)?;
+        if !city.can_move(&current, direction, heading) {
+            return None;
+        }
+        let cell = city.get_cell(next)?;
+        total += cost.cost(city, &current, cell)? as u64;
+        current = *next;
+        heading = Some(direction);
+    }
+    Some(total)
+}
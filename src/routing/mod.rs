@@ -0,0 +1,5 @@
+pub mod analysis;
+pub mod astar;
+pub mod cost;
+pub mod passability;
+pub mod planner;
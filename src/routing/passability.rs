@@ -0,0 +1,40 @@
+use crate::city::cell::{Cell, CellType, Position};
+use crate::city::grid::CityGrid;
+use serde::{Deserialize, Serialize};
+
+/// which cells agents may travel through
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PassabilityRules {
+    /// cell types open to through traffic, every other non-empty cell
+    /// can only be entered as a trip destination from an adjacent through cell
+    pub through_traffic: Vec<CellType>,
+}
+
+impl Default for PassabilityRules {
+    fn default() -> Self {
+        Self {
+            through_traffic: vec![CellType::Road],
+        }
+    }
+}
+
+impl PassabilityRules {
+    pub fn is_through(&self, cell_type: CellType) -> bool {
+        cell_type != CellType::Empty && self.through_traffic.contains(&cell_type)
+    }
+
+    /// whether a trip heading to `goal` may move from `from` into `to`
+    pub fn allows(&self, city: &CityGrid, from: &Position, to: &Cell, goal: &Position) -> bool {
+        match to.cell_type {
+            CellType::Empty => false,
+            t if self.is_through(t) => true,
+            _ => {
+                to.position == *goal
+                    && city
+                        .get_cell(from)
+                        .is_some_and(|cell| self.is_through(cell.cell_type))
+            }
+        }
+    }
+}
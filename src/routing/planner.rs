@@ -0,0 +1,87 @@
+use crate::city::cell::{Cell, Direction, Position};
+use crate::city::grid::CityGrid;
+use crate::routing::astar::Router;
+use crate::routing::cost::{path_cost, CellCost};
+use crate::routing::passability::PassabilityRules;
+
+/// router, cost function and passability rules used to plan agent trips
+#[derive(Clone, Copy)]
+pub struct RoutePlanner<'a> {
+    pub router: &'a dyn Router,
+    pub cost: &'a dyn CellCost,
+    pub rules: &'a PassabilityRules,
+}
+
+/// `cost` restricted to the moves allowed on a trip to `goal`
+struct TripCost<'a> {
+    cost: &'a dyn CellCost,
+    rules: &'a PassabilityRules,
+    goal: Position,
+}
+
+impl CellCost for TripCost<'_> {
+    fn cost(&self, city: &CityGrid, from: &Position, to: &Cell) -> Option<u32> {
+        if !self.rules.allows(city, from, to, &self.goal) {
+            return None;
+        }
+        self.cost.cost(city, from, to)
+    }
+
+    fn min_cost(&self) -> u32 {
+        self.cost.min_cost()
+    }
+}
+
+impl<'a> RoutePlanner<'a> {
+    fn trip_cost(&self, goal: Position) -> TripCost<'a> {
+        TripCost {
+            cost: self.cost,
+            rules: self.rules,
+            goal,
+        }
+    }
+
+    /// path from `from` (exclusive) to `goal` (inclusive) for an agent
+    /// that entered `from` with `heading`
+    pub fn plan(
+        &self,
+        city: &CityGrid,
+        from: Position,
+        goal: Position,
+        heading: Option<Direction>,
+    ) -> Option<Vec<Position>> {
+        self.router
+            .find_path(city, from, goal, heading, &self.trip_cost(goal), None)
+    }
+
+    /// like `plan`, but only a path costing at most `max_cost`
+    pub fn plan_within(
+        &self,
+        city: &CityGrid,
+        from: Position,
+        goal: Position,
+        heading: Option<Direction>,
+        max_cost: u64,
+    ) -> Option<Vec<Position>> {
+        self.router.find_path(
+            city,
+            from,
+            goal,
+            heading,
+            &self.trip_cost(goal),
+            Some(max_cost),
+        )
+    }
+
+    /// cost of following `path` from `from`, `None` if the path is no longer allowed
+    pub fn path_cost(
+        &self,
+        city: &CityGrid,
+        from: &Position,
+        path: &[Position],
+        heading: Option<Direction>,
+    ) -> Option<u64> {
+        let goal = *path.last()?;
+        path_cost(city, from, path, heading, &self.trip_cost(goal))
+    }
+}
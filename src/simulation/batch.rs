@@ -0,0 +1,297 @@
+use crate::agent::agent::AgentId;
+use crate::agent::mode::TransportMode;
+use crate::agent::trip::{AgentTrip, Trip};
+use crate::simulation::clock::ClockState;
+use crate::simulation::metrics::SimulationMetrics;
+use crate::simulation::simulation::{AgentUpdate, Simulation, SimulationUpdate, WorldTime};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+pub enum OutputFormat {
+    Jsonl,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            // one JSON record per line, also for `.json` files
+            "jsonl" | "ndjson" | "json" => Ok(OutputFormat::Jsonl),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!(
+                "unknown output format: {}, expected jsonl (also json, ndjson) or csv",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Jsonl => write!(f, "jsonl"),
+            OutputFormat::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    pub ticks: u64,
+    pub format: OutputFormat,
+    pub include_agents: bool,
+    pub include_trips: bool,
+}
+
+/// writes simulation updates as they are produced by a headless run
+pub struct UpdateWriter<W: Write> {
+    format: OutputFormat,
+    include_agents: bool,
+    out: W,
+    // CSV only: agent rows go to their own table
+    agents_out: Option<W>,
+    // completed trips, one per line or row
+    trips_out: Option<W>,
+    header_written: bool,
+}
+
+impl<W: Write> UpdateWriter<W> {
+    /// `agents_out` is only used by CSV output,
+    /// JSONL keeps agents inline with each update
+    pub fn new(
+        options: &BatchOptions,
+        out: W,
+        agents_out: Option<W>,
+        trips_out: Option<W>,
+    ) -> Self {
+        Self {
+            format: options.format,
+            include_agents: options.include_agents,
+            out,
+            agents_out,
+            trips_out,
+            header_written: false,
+        }
+    }
+
+    pub fn write(&mut self, update: &SimulationUpdate) -> Result<(), anyhow::Error> {
+        match self.format {
+            OutputFormat::Jsonl => self.write_jsonl(update),
+            OutputFormat::Csv => self.write_csv(update),
+        }
+    }
+
+    /// trips completed on a tick, in the update format
+    pub fn write_trips(&mut self, trips: &[AgentTrip]) -> Result<(), anyhow::Error> {
+        let Some(trips_out) = self.trips_out.as_mut() else {
+            return Ok(());
+        };
+        for record in trips {
+            match self.format {
+                OutputFormat::Jsonl => {
+                    serde_json::to_writer(&mut *trips_out, record)?;
+                    trips_out.write_all(b"\n")?;
+                }
+                OutputFormat::Csv => write_trip_csv_row(trips_out, record.agent, &record.trip)?,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), anyhow::Error> {
+        self.out.flush()?;
+        if let Some(agents_out) = self.agents_out.as_mut() {
+            agents_out.flush()?;
+        }
+        if let Some(trips_out) = self.trips_out.as_mut() {
+            trips_out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn write_jsonl(&mut self, update: &SimulationUpdate) -> Result<(), anyhow::Error> {
+        if self.include_agents {
+            serde_json::to_writer(&mut self.out, update)?;
+        } else {
+            serde_json::to_writer(
+                &mut self.out,
+                &SimulationUpdate {
+                    timestamp: update.timestamp,
+                    city_revision: update.city_revision,
+                    clock: update.clock,
+                    signals: update.signals.clone(),
+                    agents: vec![],
+                    vehicles: update.vehicles.clone(),
+                    metrics: update.metrics.clone(),
+                },
+            )?;
+        }
+        self.out.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn write_csv(&mut self, update: &SimulationUpdate) -> Result<(), anyhow::Error> {
+        if !self.header_written {
+            writeln!(self.out, "{}", METRICS_CSV_HEADER)?;
+            if let Some(agents_out) = self.agents_out.as_mut() {
+                writeln!(agents_out, "{}", AGENTS_CSV_HEADER)?;
+            }
+            if let Some(trips_out) = self.trips_out.as_mut() {
+                writeln!(trips_out, "{}", TRIPS_CSV_HEADER)?;
+            }
+            self.header_written = true;
+        }
+        write_metrics_csv_row(&mut self.out, &update.clock, &update.metrics)?;
+        if self.include_agents {
+            if let Some(agents_out) = self.agents_out.as_mut() {
+                for agent in &update.agents {
+                    write_agent_csv_row(agents_out, update.timestamp, agent)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+const METRICS_CSV_HEADER: &str = "timestamp,day,time_of_day,average_commute_time,average_distance,\
+most_congested_x,most_congested_y,max_congestion,total_reroutes,queued_agents,average_wait_time,energy_usage,\
+walk_trips,cycle_trips,drive_trips,transit_trips,\
+transit_riders,transit_waiting,transit_boardings,transit_transfers,transit_average_wait_time,\
+charging_demand,charging,charging_queued,charging_sessions,energy_charged,charging_average_queue_time,\
+commute_trips,commute_median_time,commute_p90_time,commute_p99_time";
+
+const AGENTS_CSV_HEADER: &str = "timestamp,id,x,y,state,mode";
+
+const TRIPS_CSV_HEADER: &str = "agent,purpose,commute,mode,\
+origin_x,origin_y,destination_x,destination_y,departure,arrival,duration,distance,wait_time,route";
+
+fn write_metrics_csv_row<W: Write>(
+    out: &mut W,
+    clock: &ClockState,
+    metrics: &SimulationMetrics,
+) -> Result<(), anyhow::Error> {
+    let (x, y) = match metrics.most_congested_position {
+        Some(p) => (p.x.to_string(), p.y.to_string()),
+        None => (String::new(), String::new()),
+    };
+    let trips = |mode| metrics.trips_by_mode.get(&mode).copied().unwrap_or(0);
+    writeln!(
+        out,
+        "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+        metrics.timestamp,
+        clock.day,
+        clock.time_of_day,
+        metrics.average_commute_time,
+        metrics.average_distance,
+        x,
+        y,
+        metrics.max_congestion,
+        metrics.total_reroutes,
+        metrics.queued_agents,
+        metrics.average_wait_time,
+        metrics.energy_usage,
+        trips(TransportMode::Walk),
+        trips(TransportMode::Cycle),
+        trips(TransportMode::Drive),
+        trips(TransportMode::Transit),
+        metrics.transit.riders,
+        metrics.transit.waiting,
+        metrics.transit.boardings,
+        metrics.transit.transfers,
+        metrics.transit.average_wait_time,
+        metrics.charging.demand,
+        metrics.charging.charging,
+        metrics.charging.queued,
+        metrics.charging.sessions,
+        metrics.charging.energy_charged,
+        metrics.charging.average_queue_time,
+        metrics.commute_times.trips,
+        metrics.commute_times.median,
+        metrics.commute_times.p90,
+        metrics.commute_times.p99
+    )?;
+    Ok(())
+}
+
+fn write_agent_csv_row<W: Write>(
+    out: &mut W,
+    timestamp: WorldTime,
+    agent: &AgentUpdate,
+) -> Result<(), anyhow::Error> {
+    writeln!(
+        out,
+        "{},{},{},{},{:?},{:?}",
+        timestamp, agent.id, agent.position.x, agent.position.y, agent.state, agent.mode
+    )?;
+    Ok(())
+}
+
+/// `route` lists the cells as `x:y` separated by spaces
+fn write_trip_csv_row<W: Write>(
+    out: &mut W,
+    agent: AgentId,
+    trip: &Trip,
+) -> Result<(), anyhow::Error> {
+    let optional = |t: Option<WorldTime>| t.map(|t| t.to_string()).unwrap_or_default();
+    let route = trip
+        .route
+        .iter()
+        .map(|p| format!("{}:{}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ");
+    writeln!(
+        out,
+        "{},{:?},{},{:?},{},{},{},{},{},{},{},{},{},{}",
+        agent,
+        trip.purpose,
+        trip.commute,
+        trip.mode,
+        trip.origin.x,
+        trip.origin.y,
+        trip.destination.x,
+        trip.destination.y,
+        trip.departure,
+        optional(trip.arrival),
+        optional(trip.duration()),
+        trip.distance(),
+        trip.wait_time,
+        route
+    )?;
+    Ok(())
+}
+
+/// run the simulation for a fixed number of ticks as fast as possible,
+/// without the websocket server and its tick clock
+pub fn run_batch<W: Write>(
+    sim: &mut Simulation,
+    options: &BatchOptions,
+    writer: &mut UpdateWriter<W>,
+) -> Result<(), anyhow::Error> {
+    let started = Instant::now();
+    let report_every = (options.ticks / 10).max(1);
+    for tick in 1..=options.ticks {
+        let update = sim.do_tick();
+        writer.write(&update)?;
+        if options.include_trips {
+            writer.write_trips(&sim.completed_trips)?;
+        }
+        if tick % report_every == 0 {
+            log::info!("batch run: tick {}/{}", tick, options.ticks);
+        }
+    }
+    writer.flush()?;
+    let elapsed = started.elapsed().as_secs_f64();
+    log::info!(
+        "batch run finished: {} ticks in {:.3}s ({:.1} TPS)",
+        options.ticks,
+        elapsed,
+        options.ticks as f64 / elapsed.max(f64::EPSILON)
+    );
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use crate::agent::agent::{Agent, AgentId};
+use crate::city::cell::{CellType, Position};
+use crate::city::grid::CityGrid;
+use crate::simulation::config::BatteryConfig;
+use crate::simulation::simulation::WorldTime;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// charging at energy stations so far
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChargingMetrics {
+    pub demand: usize,   // drivers heading to, queued or charging at a station
+    pub charging: usize, // agents on a charging slot
+    pub queued: usize,   // agents waiting for a free slot
+    pub sessions: usize,
+    pub energy_charged: f64,     // cells of range
+    pub average_queue_time: f64, // ticks per session
+    pub empty_batteries: usize,  // cars stopped empty, charging at the roadside
+    /// agents waiting per station ("x,y"), stations that were never used are left out
+    pub queue_lengths: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Station {
+    charging: Vec<AgentId>,
+    queue: VecDeque<(AgentId, WorldTime)>, // arrival order
+}
+
+/// charging slots and queues of the energy stations
+#[derive(Debug, Clone, Default)]
+pub struct Charging {
+    stations: BTreeMap<Position, Station>, // ordered so the charged energy sums up the same every run
+    sessions: usize,
+    energy_charged: f64,
+    total_queue_time: WorldTime,
+}
+
+impl Charging {
+    /// queue arriving drivers, charge the plugged in ones and hand free slots
+    /// to the queue in arrival order, fully charged agents continue their trip.
+    /// cars stopped with an empty battery charge at the roadside up to the reserve
+    pub fn step(
+        &mut self,
+        now: WorldTime,
+        agents: &mut [Agent],
+        city: &CityGrid,
+        battery: &BatteryConfig,
+    ) {
+        if !battery.enabled() {
+            return;
+        }
+        for agent in agents.iter_mut().filter(|a| a.stranded_since.is_some()) {
+            let charge = battery
+                .charge_rate
+                .min(battery.range - agent.battery)
+                .max(0.0);
+            agent.battery += charge;
+            self.energy_charged += charge;
+            if agent.battery >= (battery.range * battery.reserve).max(1.0) {
+                agent.stranded_since = None;
+            }
+        }
+        for agent in agents.iter_mut() {
+            let Some(position) = agent.station else {
+                continue;
+            };
+            // the station was edited away, look for another one next tick
+            if city
+                .get_cell(&position)
+                .is_none_or(|cell| cell.cell_type != CellType::EnergyStation)
+            {
+                agent.station = None;
+                continue;
+            }
+            if agent.position != position {
+                continue;
+            }
+            let station = self.stations.entry(position).or_default();
+            if !station.charging.contains(&agent.id)
+                && !station.queue.iter().any(|(id, _)| *id == agent.id)
+            {
+                station.queue.push_back((agent.id, now));
+            }
+        }
+
+        for (position, station) in &mut self.stations {
+            // agents relocated by an edit are gone
+            let present = |id: AgentId| {
+                let agent = &agents[id as usize];
+                agent.station == Some(*position) && agent.position == *position
+            };
+            station.queue.retain(|(id, _)| present(*id));
+            station.charging.retain(|id| present(*id));
+
+            station.charging.retain(|id| {
+                let agent = &mut agents[*id as usize];
+                let charge = battery
+                    .charge_rate
+                    .min(battery.range - agent.battery)
+                    .max(0.0);
+                agent.battery += charge;
+                self.energy_charged += charge;
+                let full = agent.battery >= battery.range;
+                if full {
+                    agent.station = None;
+                }
+                !full
+            });
+
+            let slots = city.get_cell(position).and_then(|cell| cell.places);
+            while slots.is_none_or(|n| station.charging.len() < n) {
+                let Some((id, since)) = station.queue.pop_front() else {
+                    break;
+                };
+                station.charging.push(id);
+                agents[id as usize].add_wait(now - since);
+                self.sessions += 1;
+                self.total_queue_time += now - since;
+            }
+        }
+    }
+
+    pub fn metrics(&self, agents: &[Agent], battery: &BatteryConfig) -> ChargingMetrics {
+        ChargingMetrics {
+            demand: agents.iter().filter(|a| a.station.is_some()).count(),
+            charging: self.stations.values().map(|s| s.charging.len()).sum(),
+            queued: self.stations.values().map(|s| s.queue.len()).sum(),
+            sessions: self.sessions,
+            energy_charged: self.energy_charged,
+            average_queue_time: self.total_queue_time as f64 / self.sessions.max(1) as f64,
+            empty_batteries: if battery.enabled() {
+                agents.iter().filter(|a| a.stranded_since.is_some()).count()
+            } else {
+                0
+            },
+            queue_lengths: self
+                .stations
+                .iter()
+                .map(|(p, s)| (format!("{},{}", p.x, p.y), s.queue.len()))
+                .collect(),
+        }
+    }
+}
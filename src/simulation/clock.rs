@@ -0,0 +1,61 @@
+use crate::simulation::simulation::WorldTime;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// maps ticks to simulated wall-clock time
+/// one tick is `1 / tick_rate` simulated seconds
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SimClock {
+    pub tick_rate: i64,
+    pub day_length: Duration,
+}
+
+impl SimClock {
+    pub fn new(tick_rate: i64, day_length: Duration) -> Self {
+        Self {
+            tick_rate: tick_rate.max(1),
+            day_length,
+        }
+    }
+
+    /// number of ticks covering the duration, rounded up
+    pub fn ticks_for(&self, duration: Duration) -> WorldTime {
+        (duration.as_secs_f64() * self.tick_rate as f64).ceil() as WorldTime
+    }
+
+    /// simulated time elapsed since tick 0
+    pub fn elapsed(&self, now: WorldTime) -> Duration {
+        Duration::from_secs_f64(now.max(0) as f64 / self.tick_rate as f64)
+    }
+
+    /// number of ticks in a simulated day, at least one
+    pub fn day_ticks(&self) -> WorldTime {
+        self.ticks_for(self.day_length).max(1)
+    }
+
+    /// zero-based day counter
+    pub fn day(&self, now: WorldTime) -> u64 {
+        (now.max(0) / self.day_ticks()) as u64
+    }
+
+    /// simulated time since the start of the current day
+    pub fn time_of_day(&self, now: WorldTime) -> Duration {
+        self.elapsed(now.max(0) % self.day_ticks())
+    }
+}
+
+/// snapshot of the clock sent along with each tick update
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ClockState {
+    pub day: u64,
+    pub time_of_day: f64, // seconds since the start of the day
+}
+
+impl ClockState {
+    pub fn at(clock: &SimClock, now: WorldTime) -> Self {
+        Self {
+            day: clock.day(now),
+            time_of_day: clock.time_of_day(now).as_secs_f64(),
+        }
+    }
+}
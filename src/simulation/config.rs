@@ -1,12 +1,209 @@
+use crate::agent::mode::TransportMode;
+use crate::agent::schedule::{Activity, Destination, Stay};
+use crate::city::cell::CellType;
+use crate::routing::passability::PassabilityRules;
+use crate::simulation::clock::SimClock;
+use crate::simulation::simulation::WorldTime;
 use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
 use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SimulationConfig {
     pub num_agents: usize,
     pub tick_rate: i64, // TPS, tick per second
+    /// kinds of agents, the population is split by their shares
+    pub archetypes: Vec<Archetype>,
+    /// stay at work, home or the park for activities without `duration` or `until`
     pub work_duration: Duration,
     pub home_duration: Duration,
+    pub park_duration: Duration,
+    /// length of a simulated day, drives the time-of-day clock
+    pub day_length: Duration,
+    /// per-agent random spread of dwell durations, as a fraction (0.2 = +/-20%)
+    pub dwell_jitter: f64,
+    /// per-agent random shift of the times of day in schedules, up to this much earlier or later
+    pub time_jitter: Duration,
+    pub routing: RoutingConfig,
+    pub transport: TransportConfig,
+    /// ticks an agent waits in front of a full cell before re-planning around it,
+    /// breaks gridlocks where queues block each other in a cycle, 0 disables
+    pub gridlock_timeout: i64,
+    /// completed trips kept per agent for the agent trips API, older ones are dropped
+    /// there but stay in the trip log and the exports
+    pub trip_history: usize,
+    /// completed trips the server keeps for the bulk trips API, the oldest are dropped first
+    pub trip_log: usize,
+    /// width in ticks of the trip time histogram bins
+    pub trip_time_bin: WorldTime,
+    /// RNG seed, runs with the same city and config are reproducible
+    pub seed: u64,
+}
+
+impl SimulationConfig {
+    /// load from a JSON file, missing fields fall back to defaults
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let file = File::open(path)
+            .map_err(|e| format!("failed to open simulation config file: {}", e))?;
+        let reader = BufReader::new(file);
+        let config: Self = serde_json::from_reader(reader)
+            .map_err(|e| format!("failed to parse simulation config: {}", e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// check the tick rate, archetypes and transport modes, the first problem found is returned
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tick_rate <= 0 {
+            return Err("tick rate must be positive".to_string());
+        }
+        if self.archetypes.is_empty() {
+            return Err("at least one archetype is required".to_string());
+        }
+        for archetype in &self.archetypes {
+            if !archetype.share.is_finite() || archetype.share < 0.0 {
+                return Err(format!(
+                    "archetype {:?}: share must be a non-negative number",
+                    archetype.name
+                ));
+            }
+            if archetype.schedule.is_empty() {
+                return Err(format!("archetype {:?}: empty schedule", archetype.name));
+            }
+            if archetype
+                .schedule
+                .iter()
+                .any(|a| a.until.is_some_and(|until| until >= self.day_length))
+            {
+                return Err(format!(
+                    "archetype {:?}: `until` must be shorter than the day length",
+                    archetype.name
+                ));
+            }
+        }
+        if self.archetypes.iter().all(|a| a.share == 0.0) {
+            return Err("archetype shares add up to zero".to_string());
+        }
+        for mode in TransportMode::ALL {
+            let config = self.transport.mode(mode);
+            if !config.speed.is_finite() || config.speed <= 0.0 {
+                return Err(format!("transport mode {:?}: speed must be positive", mode));
+            }
+            if !(0.0..=1.0).contains(&config.ownership) {
+                return Err(format!(
+                    "transport mode {:?}: ownership must be between 0 and 1",
+                    mode
+                ));
+            }
+        }
+        if self.trip_time_bin <= 0 {
+            return Err("trip time bin must be positive".to_string());
+        }
+        let battery = &self.transport.battery;
+        if !battery.range.is_finite() || battery.range < 0.0 {
+            return Err("battery range must be a non-negative number".to_string());
+        }
+        if !(0.0..=1.0).contains(&battery.reserve) {
+            return Err("battery reserve must be between 0 and 1".to_string());
+        }
+        if battery.enabled() && !(battery.charge_rate.is_finite() && battery.charge_rate > 0.0) {
+            return Err("battery charge rate must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn clock(&self) -> SimClock {
+        SimClock::new(self.tick_rate, self.day_length)
+    }
+
+    /// cells agents travelling by `mode` may pass through
+    pub fn passability(&self, mode: TransportMode) -> &PassabilityRules {
+        self.transport
+            .mode(mode)
+            .passability
+            .as_ref()
+            .unwrap_or(&self.routing.passability)
+    }
+
+    /// agents per archetype, shares are rounded so the counts add up to `num_agents`
+    pub fn population(&self) -> Vec<usize> {
+        let total: f64 = self.archetypes.iter().map(|a| a.share.max(0.0)).sum();
+        if total <= 0.0 {
+            return vec![0; self.archetypes.len()];
+        }
+        let exact = self
+            .archetypes
+            .iter()
+            .map(|a| a.share.max(0.0) / total * self.num_agents as f64)
+            .collect::<Vec<_>>();
+        let mut counts = exact.iter().map(|n| n.floor() as usize).collect::<Vec<_>>();
+        let rest = self.num_agents.saturating_sub(counts.iter().sum());
+        // largest remainders get the agents lost to rounding, earlier archetypes win ties
+        let mut order = (0..exact.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| exact[b].fract().total_cmp(&exact[a].fract()));
+        for i in order.into_iter().take(rest) {
+            counts[i] += 1;
+        }
+        counts
+    }
+
+    /// places the population needs per cell type: a home for everyone and
+    /// a workplace for every agent whose schedule includes work
+    pub fn place_demand(&self) -> Vec<(CellType, usize)> {
+        let mut demand = vec![(CellType::House, self.num_agents)];
+        for (archetype, count) in self.archetypes.iter().zip(self.population()) {
+            if !archetype.works() {
+                continue;
+            }
+            match demand.iter_mut().find(|(t, _)| *t == archetype.work_type) {
+                Some((_, n)) => *n += count,
+                None => demand.push((archetype.work_type, count)),
+            }
+        }
+        demand
+    }
+
+    /// the archetype's schedule in ticks, `factor` scales every duration and
+    /// `shift` ticks are added to every time of day
+    pub fn schedule(&self, archetype: &Archetype, factor: f64, shift: WorldTime) -> Vec<Activity> {
+        let clock = self.clock();
+        let day_ticks = clock.day_ticks();
+        archetype
+            .schedule
+            .iter()
+            .map(|activity| Activity {
+                destination: activity.destination,
+                stay: match activity.until {
+                    Some(until) => {
+                        Stay::Until((clock.ticks_for(until) + shift).rem_euclid(day_ticks))
+                    }
+                    None => Stay::For(jittered_ticks(
+                        &clock,
+                        activity
+                            .duration
+                            .unwrap_or(self.dwell_duration(activity.destination)),
+                        factor,
+                    )),
+                },
+            })
+            .collect()
+    }
+
+    fn dwell_duration(&self, destination: Destination) -> Duration {
+        match destination {
+            Destination::Home => self.home_duration,
+            Destination::Work => self.work_duration,
+            Destination::Park => self.park_duration,
+        }
+    }
+}
+
+/// duration in ticks, scaled by the agent's jitter factor
+fn jittered_ticks(clock: &SimClock, duration: Duration, factor: f64) -> WorldTime {
+    (clock.ticks_for(duration) as f64 * factor).round() as WorldTime
 }
 
 impl Default for SimulationConfig {
@@ -14,8 +211,328 @@ impl Default for SimulationConfig {
         Self {
             num_agents: 20,
             tick_rate: 10,
+            archetypes: default_archetypes(),
             work_duration: Duration::from_secs(30),
             home_duration: Duration::from_secs(30),
+            park_duration: Duration::from_secs(10),
+            day_length: Duration::from_secs(120),
+            dwell_jitter: 0.0,
+            time_jitter: Duration::ZERO,
+            routing: RoutingConfig::default(),
+            transport: TransportConfig::default(),
+            gridlock_timeout: 30,
+            trip_history: 20,
+            trip_log: 10_000,
+            trip_time_bin: 10,
+            seed: 0,
+        }
+    }
+}
+
+/// a kind of agent with its own daily routine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Archetype {
+    pub name: String,
+    /// relative share of the population
+    pub share: f64,
+    /// cell type agents work at, e.g. offices double as schools for students
+    pub work_type: CellType,
+    /// activities in order, repeated after the last one, agents start at home
+    /// and begin with the first activity
+    pub schedule: Vec<ActivityConfig>,
+}
+
+impl Archetype {
+    /// whether agents of this archetype need a workplace
+    pub fn works(&self) -> bool {
+        self.schedule
+            .iter()
+            .any(|a| a.destination == Destination::Work)
+    }
+}
+
+impl Default for Archetype {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            share: 1.0,
+            work_type: CellType::Office,
+            schedule: vec![
+                ActivityConfig::new(Destination::Work),
+                ActivityConfig::new(Destination::Home),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityConfig {
+    pub destination: Destination,
+    /// how long to stay, `home_duration`, `work_duration` or `park_duration` if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<Duration>,
+    /// stay until this time of day instead, since the start of the day
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<Duration>,
+}
+
+impl ActivityConfig {
+    pub fn new(destination: Destination) -> Self {
+        Self {
+            destination,
+            duration: None,
+            until: None,
+        }
+    }
+
+    fn stay_for(destination: Destination, duration: Duration) -> Self {
+        Self {
+            duration: Some(duration),
+            ..Self::new(destination)
+        }
+    }
+
+    fn stay_until(destination: Destination, until: Duration) -> Self {
+        Self {
+            until: Some(until),
+            ..Self::new(destination)
+        }
+    }
+}
+
+/// a mixed population for the default 120 s day, an hour is 5 s
+fn default_archetypes() -> Vec<Archetype> {
+    use Destination::{Home, Park, Work};
+    let hours = |h: f64| Duration::from_secs_f64(h * 5.0);
+    let archetype = |name: &str, share: f64, schedule: Vec<ActivityConfig>| Archetype {
+        name: name.to_string(),
+        share,
+        work_type: CellType::Office,
+        schedule,
+    };
+    vec![
+        archetype(
+            "office worker",
+            0.5,
+            vec![
+                ActivityConfig::stay_until(Work, hours(17.0)),
+                ActivityConfig::stay_for(Park, hours(1.0)),
+                ActivityConfig::stay_until(Home, hours(7.0)),
+            ],
+        ),
+        archetype(
+            "shift worker",
+            0.15,
+            vec![
+                ActivityConfig::stay_until(Work, hours(7.0)),
+                ActivityConfig::stay_until(Home, hours(19.0)),
+            ],
+        ),
+        archetype(
+            "student",
+            0.15,
+            vec![
+                ActivityConfig::stay_until(Work, hours(15.0)),
+                ActivityConfig::stay_for(Park, hours(2.0)),
+                ActivityConfig::stay_until(Home, hours(7.5)),
+            ],
+        ),
+        archetype(
+            "retiree",
+            0.1,
+            vec![
+                ActivityConfig::stay_until(Home, hours(9.0)),
+                ActivityConfig::stay_for(Park, hours(3.0)),
+                ActivityConfig::stay_until(Home, hours(15.0)),
+                ActivityConfig::stay_for(Park, hours(2.0)),
+            ],
+        ),
+        archetype(
+            "remote worker",
+            0.1,
+            vec![
+                ActivityConfig::stay_until(Home, hours(12.0)),
+                ActivityConfig::stay_for(Park, hours(1.0)),
+            ],
+        ),
+    ]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoutingConfig {
+    /// extra cost per occupant on a cell, 0 routes by distance only
+    pub congestion_weight: f64,
+    /// weight of the latest tick in the observed occupancy average, in (0, 1]
+    pub occupancy_smoothing: f64,
+    /// re-check every trip this often, in ticks, 0 disables rerouting
+    pub reroute_interval: i64,
+    /// switch route when the remaining path costs this many times the best alternative
+    pub reroute_threshold: f64,
+    /// where agents may travel, add `Park` to allow pedestrian shortcuts
+    pub passability: PassabilityRules,
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self {
+            congestion_weight: 1.0,
+            occupancy_smoothing: 0.2,
+            reroute_interval: 10,
+            reroute_threshold: 1.5,
+            passability: PassabilityRules::default(),
+        }
+    }
+}
+
+/// transport modes and how agents choose between them
+///
+/// an agent owns a bike or a car with the mode's `ownership` probability and
+/// picks the slowest mode it owns whose `max_distance` covers the trip,
+/// or its fastest mode for longer trips. it takes transit instead when the
+/// itinerary is estimated to be quicker than that mode at full speed, or when
+/// the trip is longer than the `max_distance` of every mode it owns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransportConfig {
+    pub walk: ModeConfig,
+    pub cycle: ModeConfig,
+    pub drive: ModeConfig,
+    pub transit: TransitConfig,
+    pub battery: BatteryConfig,
+}
+
+impl TransportConfig {
+    /// transit riders walk to and from stops
+    pub fn mode(&self, mode: TransportMode) -> &ModeConfig {
+        match mode {
+            TransportMode::Walk | TransportMode::Transit => &self.walk,
+            TransportMode::Cycle => &self.cycle,
+            TransportMode::Drive => &self.drive,
+        }
+    }
+
+    /// mode for a trip of `distance` cells (Manhattan) among the agent's `available` modes
+    pub fn choose(&self, available: &[TransportMode], distance: usize) -> TransportMode {
+        available
+            .iter()
+            .copied()
+            .find(|m| self.mode(*m).max_distance.is_none_or(|max| distance <= max))
+            .or_else(|| available.iter().copied().max())
+            .unwrap_or(TransportMode::Walk)
+    }
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        let pedestrian = PassabilityRules {
+            through_traffic: vec![CellType::Road, CellType::Park],
+        };
+        Self {
+            walk: ModeConfig {
+                speed: 0.5,
+                ownership: 1.0,
+                max_distance: Some(8),
+                energy_per_cell: 0.0,
+                passability: Some(pedestrian.clone()),
+            },
+            cycle: ModeConfig {
+                speed: 1.0,
+                ownership: 0.5,
+                max_distance: Some(30),
+                energy_per_cell: 0.05,
+                passability: Some(pedestrian),
+            },
+            drive: ModeConfig {
+                speed: 2.0,
+                ownership: 0.7,
+                max_distance: None,
+                energy_per_cell: 0.25,
+                passability: None,
+            },
+            transit: TransitConfig::default(),
+            battery: BatteryConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransitConfig {
+    /// farthest walk to the first or from the last stop, in cells (Manhattan)
+    pub max_walk: usize,
+}
+
+impl Default for TransitConfig {
+    fn default() -> Self {
+        Self { max_walk: 6 }
+    }
+}
+
+/// car batteries, drained by driving and recharged at energy stations
+///
+/// a driver starting a trip below the reserve detours to the nearest energy station and
+/// waits there for one of its charging slots (the station's `places`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BatteryConfig {
+    /// cells driven on a full battery, 0 disables batteries
+    pub range: f64,
+    /// fraction of the range below which drivers head for a station
+    pub reserve: f64,
+    /// cells of range added per tick on a charging slot
+    pub charge_rate: f64,
+}
+
+impl BatteryConfig {
+    pub fn enabled(&self) -> bool {
+        self.range > 0.0
+    }
+
+    pub fn is_low(&self, battery: f64) -> bool {
+        self.enabled() && battery < self.range * self.reserve
+    }
+
+    /// whether a car with `battery` left cannot drive another cell
+    pub fn is_empty(&self, battery: f64) -> bool {
+        self.enabled() && battery < 1.0
+    }
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        Self {
+            range: 80.0,
+            reserve: 0.25,
+            charge_rate: 4.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModeConfig {
+    /// cells per tick, capped by speed limits, fractions carry over to the next tick
+    pub speed: f64,
+    /// probability an agent can use this mode, everyone can walk
+    pub ownership: f64,
+    /// longest trip in cells (Manhattan) this mode is preferred for, unlimited if `None`
+    pub max_distance: Option<usize>,
+    pub energy_per_cell: f64,
+    /// where this mode may travel, `routing.passability` if `None`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub passability: Option<PassabilityRules>,
+}
+
+impl Default for ModeConfig {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            ownership: 1.0,
+            max_distance: None,
+            energy_per_cell: 0.0,
+            passability: None,
         }
     }
 }
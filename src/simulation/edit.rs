@@ -0,0 +1,200 @@
+use crate::agent::agent::AgentId;
+use crate::agent::mode::TransportMode;
+use crate::city::cell::{Cell, CellType, Position};
+use crate::city::config::CellConfig;
+use crate::routing::passability::PassabilityRules;
+use crate::simulation::simulation::Simulation;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// outcome of editing the map of a running simulation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EditReport {
+    pub revision: u64, // city grid revision after the edit
+    /// agents moved off cells they can no longer stand on
+    pub relocated: Vec<AgentId>,
+    /// agents whose home, work or park moved to another cell
+    pub reassigned: Vec<AgentId>,
+}
+
+impl Simulation {
+    /// change cells while the simulation runs, each edit describes the whole
+    /// cell like in the city config, starting from the capacity and places
+    /// the city configures for its type
+    ///
+    /// all edits are checked before any is applied, cells transit vehicles drive
+    /// over must stay open to through traffic. agents re-plan routes through
+    /// edited cells on the next tick, and all routes when a cell was opened to
+    /// through traffic of any mode
+    pub fn edit_cells(&mut self, edits: &[CellConfig]) -> Result<EditReport, String> {
+        let mut changes = Vec::with_capacity(edits.len());
+        for edit in edits {
+            let cell_type = CellType::from_str(&edit.cell_type)
+                .map_err(|_| format!("unknown cell type {:?}", edit.cell_type))?;
+            let position = Position::new(edit.x, edit.y);
+            if self.city.get_cell(&position).is_none() {
+                return Err(format!(
+                    "position ({}, {}) is out of bounds",
+                    position.x, position.y
+                ));
+            }
+            let mut cell = Cell::new(cell_type, position);
+            edit.apply_to(&mut cell);
+            let rules = &self.config.routing.passability;
+            if !(rules.is_through(cell.cell_type) && cell.is_open()) {
+                if let Some(line) = self.transit.lines.iter().find(|l| l.passes(&position)) {
+                    return Err(format!(
+                        "position ({}, {}) is on the route of transit line {:?}",
+                        position.x, position.y, line.name
+                    ));
+                }
+            }
+            changes.push((edit, position, cell_type));
+        }
+
+        let mut report = EditReport::default();
+        let mut closed = Vec::new();
+        for (edit, position, cell_type) in changes {
+            let Some(before) = self.city.get_cell(&position).cloned() else {
+                continue;
+            };
+            let previous = before.cell_type;
+            self.city.set_cell_type(&position, cell_type)?;
+            let capacity = self.city.type_capacity(cell_type);
+            let places = self.city.type_places(cell_type);
+            if let Some(cell) = self.city.get_cell_mut(&position) {
+                cell.capacity = capacity;
+                cell.places = places;
+                edit.apply_to(cell);
+            }
+            self.city.invalidate_cell(&position);
+            if self.opens_through_traffic(&before, &position) {
+                // a shorter route may now pass the cell anywhere
+                self.city.invalidate_routes();
+            }
+            log::info!(
+                "cell ({}, {}) edited: {} -> {}",
+                position.x,
+                position.y,
+                previous,
+                cell_type
+            );
+            if previous != cell_type {
+                self.reassign_anchors(position, previous, &mut report);
+            }
+            if self
+                .city
+                .get_cell(&position)
+                .is_some_and(|cell| !cell.is_open())
+            {
+                closed.push(position);
+            }
+        }
+        if !closed.is_empty() {
+            // one index of the cells agents can be moved to, once every edit is applied
+            let rules = &self.config.routing.passability;
+            let through = self
+                .city
+                .cells()
+                .iter()
+                .filter(|cell| rules.is_through(cell.cell_type) && cell.is_open())
+                .map(|cell| cell.position)
+                .collect::<Vec<_>>();
+            for position in closed {
+                self.relocate_agents(position, &through, &mut report);
+            }
+        }
+        report.revision = self.city.revision();
+        Ok(report)
+    }
+
+    /// whether the edited cell at `position` is passable by through traffic of a mode
+    /// that could not pass it `before`
+    fn opens_through_traffic(&self, before: &Cell, position: &Position) -> bool {
+        let Some(after) = self.city.get_cell(position) else {
+            return false;
+        };
+        let passable = |cell: &Cell, rules: &PassabilityRules| {
+            rules.is_through(cell.cell_type) && cell.is_open()
+        };
+        TransportMode::ALL.into_iter().any(|mode| {
+            let rules = self.config.passability(mode);
+            !passable(before, rules) && passable(after, rules)
+        })
+    }
+
+    /// move home, work and park of agents off `position`, which is no longer of `previous` type
+    fn reassign_anchors(
+        &mut self,
+        position: Position,
+        previous: CellType,
+        report: &mut EditReport,
+    ) {
+        let replacement = self.city.find_nearest_cell_with_type(&position, previous);
+        for agent in &mut self.agents {
+            let anchors = [&mut agent.home, &mut agent.work, &mut agent.park];
+            let mut changed = false;
+            for anchor in anchors.into_iter().filter(|a| **a == position) {
+                match replacement {
+                    Some(replacement) => {
+                        *anchor = replacement;
+                        changed = true;
+                    }
+                    None => log::warn!(
+                        "agent {}: no {} cell left to replace ({}, {})",
+                        agent.id,
+                        previous,
+                        position.x,
+                        position.y
+                    ),
+                }
+            }
+            if changed {
+                report.reassigned.push(agent.id);
+            }
+        }
+    }
+
+    /// move agents off `position`, which was closed, to the nearest of the `through`
+    /// cells, which are open to through traffic
+    fn relocate_agents(
+        &mut self,
+        position: Position,
+        through: &[Position],
+        report: &mut EditReport,
+    ) {
+        if let Some(cell) = self.city.get_cell_mut(&position) {
+            cell.occupants = 0;
+        }
+        let mut nearest = through.to_vec();
+        nearest.sort_by_key(|target| position.distance(target));
+        // riders pass over the cell on board a vehicle
+        for agent in self
+            .agents
+            .iter_mut()
+            .filter(|a| a.position == position && a.riding.is_none())
+        {
+            let id = agent.id;
+            // the closest cell with room, or the closest one when all are full
+            let target = nearest
+                .iter()
+                .find(|target| self.city.get_cell(target).is_some_and(|c| !c.is_full()))
+                .or(nearest.first())
+                .copied()
+                .unwrap_or(agent.home);
+            log::info!(
+                "agent {}: relocated from ({}, {}) to ({}, {})",
+                id,
+                position.x,
+                position.y,
+                target.x,
+                target.y
+            );
+            agent.relocate(target);
+            if let Some(cell) = self.city.get_cell_mut(&target) {
+                cell.add_occupant();
+            }
+            report.relocated.push(id);
+        }
+    }
+}
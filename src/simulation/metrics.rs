@@ -1,35 +1,148 @@
 use crate::agent::metrics::AgentMetrics;
+use crate::agent::mode::TransportMode;
+use crate::agent::schedule::Destination;
+use crate::agent::trip::Trip;
 use crate::city::cell::{CellType, Position};
 use crate::city::grid::CityGrid;
+use crate::simulation::charging::ChargingMetrics;
 use crate::simulation::simulation::WorldTime;
+use crate::simulation::transit::TransitMetrics;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SimulationMetrics {
     pub timestamp: WorldTime,
-    pub average_commute_time: f64,
+    pub average_commute_time: f64, // ticks per completed trip to or from work
+    pub commute_times: TripTimeStats,
+    /// completed trips by the activity they lead to
+    pub trip_times: BTreeMap<Destination, TripTimeStats>,
     pub average_distance: f64,
-    pub congestion_map: HashMap<String, usize>,
+    pub congestion_map: BTreeMap<String, usize>,
     pub most_congested_position: Option<Position>,
     pub max_congestion: usize,
+    pub total_reroutes: usize,
+    pub queued_agents: usize, // agents waiting in front of a full cell
+    pub average_wait_time: f64,
     pub energy_usage: f64,
+    /// trips started so far per transport mode
+    pub trips_by_mode: BTreeMap<TransportMode, usize>,
+    /// fraction of those trips per mode
+    pub mode_share: BTreeMap<TransportMode, f64>,
+    pub transit: TransitMetrics,
+    pub charging: ChargingMetrics,
+}
+
+/// durations of completed trips in ticks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TripTimeStats {
+    pub trips: usize,
+    pub mean: f64,
+    pub median: WorldTime,
+    pub p90: WorldTime,
+    pub p99: WorldTime,
+    /// trips per bin of `trip_time_bin` ticks, keyed by the shortest duration of the bin
+    pub histogram: BTreeMap<WorldTime, usize>,
+}
+
+/// trips counted per duration, quantiles are exact without keeping every trip
+#[derive(Debug, Clone, Default)]
+struct Durations {
+    counts: Vec<usize>, // indexed by duration
+    trips: usize,
+    total: WorldTime,
+}
+
+impl Durations {
+    fn add(&mut self, duration: WorldTime) {
+        let Ok(duration) = usize::try_from(duration) else {
+            log::error!("negative trip duration {}, not counted", duration);
+            return;
+        };
+        if duration >= self.counts.len() {
+            self.counts.resize(duration + 1, 0);
+        }
+        self.counts[duration] += 1;
+        self.trips += 1;
+        self.total += duration as WorldTime;
+    }
+
+    /// shortest duration that at least a fraction `q` of the trips did not exceed
+    fn quantile(&self, q: f64) -> WorldTime {
+        let rank = ((q * self.trips as f64).ceil() as usize).max(1);
+        let mut seen = 0;
+        for (duration, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return duration as WorldTime;
+            }
+        }
+        0
+    }
+
+    fn stats(&self, bin: WorldTime) -> TripTimeStats {
+        let bin = bin.max(1);
+        let mut histogram = BTreeMap::new();
+        for (duration, count) in self.counts.iter().enumerate() {
+            if *count > 0 {
+                let from = duration as WorldTime / bin * bin;
+                *histogram.entry(from).or_default() += count;
+            }
+        }
+        TripTimeStats {
+            trips: self.trips,
+            mean: self.total as f64 / self.trips.max(1) as f64,
+            median: self.quantile(0.5),
+            p90: self.quantile(0.9),
+            p99: self.quantile(0.99),
+            histogram,
+        }
+    }
+}
+
+/// durations of the trips completed so far, for commutes and by purpose
+#[derive(Debug, Clone, Default)]
+pub struct TripTimes {
+    bin: WorldTime, // histogram bin width in ticks
+    commute: Durations,
+    by_purpose: BTreeMap<Destination, Durations>,
+}
+
+impl TripTimes {
+    pub fn new(bin: WorldTime) -> Self {
+        Self {
+            bin,
+            ..Self::default()
+        }
+    }
+
+    /// count a completed trip, trips under way are ignored
+    pub fn add(&mut self, trip: &Trip) {
+        let Some(duration) = trip.duration() else {
+            return;
+        };
+        if trip.commute {
+            self.commute.add(duration);
+        }
+        self.by_purpose
+            .entry(trip.purpose)
+            .or_default()
+            .add(duration);
+    }
 }
 
 pub fn calc_metrics(
     city: &CityGrid,
     agent_metrics: &[AgentMetrics],
+    trip_times: &TripTimes,
     current_time: WorldTime,
 ) -> SimulationMetrics {
-    let total_commute_time = agent_metrics
+    let commute_times = trip_times.commute.stats(trip_times.bin);
+    let trip_times_by_purpose = trip_times
+        .by_purpose
         .iter()
-        .map(|m| m.total_commute_time)
-        .sum::<i64>() as u64;
-    let average_commute_time = if !agent_metrics.is_empty() {
-        total_commute_time as f64 / agent_metrics.len() as f64
-    } else {
-        0.0
-    };
+        .map(|(purpose, durations)| (*purpose, durations.stats(trip_times.bin)))
+        .collect();
 
     let total_distance: usize = agent_metrics.iter().map(|m| m.total_distance).sum();
     let average_distance = if !agent_metrics.is_empty() {
@@ -38,40 +151,54 @@ pub fn calc_metrics(
         0.0
     };
 
-    let mut congestion_map = HashMap::new();
+    let total_wait_time: WorldTime = agent_metrics.iter().map(|m| m.total_wait_time).sum();
+    let average_wait_time = if !agent_metrics.is_empty() {
+        total_wait_time as f64 / agent_metrics.len() as f64
+    } else {
+        0.0
+    };
+
+    let mut congestion = Vec::new();
     let mut max_congestion = 0;
     let mut most_congested_position = None;
-    for y in 0..city.height {
-        for x in 0..city.width {
-            let position = Position { x, y };
-            if let Some(cell) = city.get_cell(&position) {
-                if cell.cell_type == CellType::Road {
-                    let occupants = cell.occupant_count();
-                    if occupants > 0 {
-                        let key = format!("{},{}", x, y);
-                        congestion_map.insert(key, occupants);
-
-                        if occupants > max_congestion {
-                            max_congestion = occupants;
-                            most_congested_position = Some(position);
-                        }
-                    }
-                }
+    for cell in city.cells_of_type(CellType::Road) {
+        let occupants = cell.occupant_count();
+        if occupants > 0 {
+            let Position { x, y } = cell.position;
+            congestion.push((format!("{},{}", x, y), occupants));
+
+            if occupants > max_congestion {
+                max_congestion = occupants;
+                most_congested_position = Some(cell.position);
             }
         }
     }
+    let congestion_map = BTreeMap::from_iter(congestion);
+
+    let mut trips = [0; TransportMode::COUNT];
+    for m in agent_metrics {
+        for (total, n) in trips.iter_mut().zip(m.trips_by_mode) {
+            *total += n;
+        }
+    }
+    let total_trips: usize = trips.iter().sum();
+    let trips_by_mode = BTreeMap::from_iter(TransportMode::ALL.into_iter().zip(trips));
+    let mode_share = TransportMode::ALL
+        .into_iter()
+        .zip(trips)
+        .map(|(mode, n)| (mode, n as f64 / total_trips.max(1) as f64))
+        .collect();
 
     // simple energy cost model:
     // energy = base_energy + movement_energy + time_energy
     // base_energy: energy consumed without doing anything
-    // movement_energy: extra energy consumed when moving
+    // movement_energy: extra energy consumed when moving, per cell of the transport mode
     // time_energy: energy consumed when time is spent on commune
     let base_energy = agent_metrics.len() as f64 * 0.125;
     let movement_energy = agent_metrics
         .iter()
-        .map(|agent| agent.total_distance)
-        .sum::<usize>() as f64
-        * 0.25;
+        .map(|agent| agent.energy_used)
+        .sum::<f64>();
     let time_energy = agent_metrics
         .iter()
         .map(|agent| agent.total_commute_time)
@@ -81,11 +208,20 @@ pub fn calc_metrics(
 
     SimulationMetrics {
         timestamp: current_time,
-        average_commute_time,
+        average_commute_time: commute_times.mean,
+        commute_times,
+        trip_times: trip_times_by_purpose,
         average_distance,
         congestion_map,
         most_congested_position,
         max_congestion,
+        total_reroutes: agent_metrics.iter().map(|m| m.reroutes).sum(),
+        queued_agents: agent_metrics.iter().filter(|m| m.waiting).count(),
+        average_wait_time,
         energy_usage,
+        trips_by_mode,
+        mode_share,
+        transit: TransitMetrics::default(),
+        charging: ChargingMetrics::default(),
     }
 }
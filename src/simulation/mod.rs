@@ -1,3 +1,9 @@
+pub mod batch;
+pub mod charging;
+pub mod clock;
 pub mod config;
+pub mod edit;
 pub mod metrics;
+#[allow(clippy::module_inception)]
 pub mod simulation;
+pub mod transit;
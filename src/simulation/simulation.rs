@@ -1,13 +1,25 @@
-use crate::agent::agent::Agent;
+use crate::agent::agent::{Agent, AgentId};
 use crate::agent::metrics::AgentMetrics;
+use crate::agent::mode::TransportMode;
 use crate::agent::state::AgentState;
-use crate::city::cell::{CellType, Position};
+use crate::agent::trip::{AgentTrip, TripLog};
+use crate::city::cell::{CellType, Direction, Position};
 use crate::city::grid::CityGrid;
+use crate::city::signal::SignalState;
+use crate::city::validation::Issue;
+use crate::routing::astar::{AStar, Router};
+use crate::routing::cost::CongestionCost;
+use crate::routing::planner::RoutePlanner;
+use crate::simulation::charging::Charging;
+use crate::simulation::clock::ClockState;
 use crate::simulation::config::SimulationConfig;
-use crate::simulation::metrics::{calc_metrics, SimulationMetrics};
-use rand::Rng;
+use crate::simulation::metrics::{calc_metrics, SimulationMetrics, TripTimes};
+use crate::simulation::transit::{Transit, VehicleUpdate};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashSet};
 use std::sync::atomic::AtomicBool;
 use std::sync::{atomic, Arc};
 use std::time::Duration;
@@ -17,56 +29,143 @@ use tokio::time;
 #[derive(Debug)]
 pub struct Simulation {
     pub city: CityGrid,
-    pub agents: HashMap<String, Agent>,
+    pub agents: Vec<Agent>, // indexed by `AgentId`
     pub config: SimulationConfig,
     pub current_time: WorldTime,
+    pub rng: StdRng,
+    pub router: Box<dyn Router>,
+    pub transit: Transit,
+    pub charging: Charging,
+    pub trip_times: TripTimes,
+    /// trips completed on the last tick
+    pub completed_trips: Vec<AgentTrip>,
+    /// trips completed while the server runs, for the trips API
+    pub trip_log: TripLog,
     pub tick_updates_broadcaster: broadcast::Sender<SimulationUpdate>,
 }
 
 impl Simulation {
     pub fn new(city: CityGrid, config: SimulationConfig) -> Self {
         let (tx, _) = broadcast::channel(100);
+        let rng = StdRng::seed_from_u64(config.seed);
+        let transit = Transit::new(&city, &config.routing.passability);
+        let trip_times = TripTimes::new(config.trip_time_bin);
+        let trip_log = TripLog::new(config.trip_log);
 
         Self {
             city,
-            agents: HashMap::new(),
+            agents: Vec::new(),
             config,
             current_time: 0,
+            rng,
+            router: Box::new(AStar::default()),
+            transit,
+            charging: Charging::default(),
+            trip_times,
+            completed_trips: Vec::new(),
+            trip_log,
             tick_updates_broadcaster: tx,
         }
     }
 
-    pub fn initialize(&mut self) {
-        let mut rng = rand::rng();
-        let houses = self.city.find_cells_of_type(CellType::House);
-        let offices = self.city.find_cells_of_type(CellType::Office);
-
-        if houses.is_empty() || offices.is_empty() {
-            return;
+    pub fn initialize(&mut self) -> Result<(), Issue> {
+        let rng = &mut self.rng;
+        let mut homes = Places::new(&self.city, CellType::House);
+        if homes.cells.is_empty() {
+            return Ok(());
         }
+        let demand = self.config.place_demand();
+        let mut workplaces = Vec::<(CellType, Places)>::new();
+        let parks = self.city.find_cells_of_type(CellType::Park);
 
-        for i in 0..self.config.num_agents {
-            let home = houses[rng.random_range(0..houses.len())];
-            let work = offices[rng.random_range(0..offices.len())];
-
-            let parks = self.city.find_cells_of_type(CellType::Park);
-            let park = if !parks.is_empty() {
-                parks[rng.random_range(0..parks.len())]
-            } else {
-                work
-            };
-            let agent = Agent::new(format!("agent-{}", i), home, work, park);
-            if let Some(cell) = self.city.get_cell_mut(&home) {
-                cell.add_occupant(&agent.id);
+        let jitter = self.config.dwell_jitter.clamp(0.0, 1.0);
+        let clock = self.config.clock();
+        let time_jitter = clock.ticks_for(self.config.time_jitter);
+        self.agents.reserve(self.config.num_agents);
+        let population = self.config.population();
+        for (archetype_index, count) in population.into_iter().enumerate() {
+            let archetype = &self.config.archetypes[archetype_index];
+            for _ in 0..count {
+                let Some(home) = homes.take(rng) else {
+                    return Err(homes.shortage(&self.city, &demand));
+                };
+                let work = if archetype.works() {
+                    let i = match workplaces
+                        .iter()
+                        .position(|(t, _)| *t == archetype.work_type)
+                    {
+                        Some(i) => i,
+                        None => {
+                            let places = Places::new(&self.city, archetype.work_type);
+                            workplaces.push((archetype.work_type, places));
+                            workplaces.len() - 1
+                        }
+                    };
+                    let places = &mut workplaces[i].1;
+                    if places.cells.is_empty() {
+                        return Err(Issue::MissingCellType {
+                            cell_type: archetype.work_type,
+                        });
+                    }
+                    places
+                        .take(rng)
+                        .ok_or_else(|| places.shortage(&self.city, &demand))?
+                } else {
+                    home
+                };
+                let park = if !parks.is_empty() {
+                    parks[rng.random_range(0..parks.len())]
+                } else {
+                    work
+                };
+                let factor = if jitter > 0.0 {
+                    1.0 + rng.random_range(-jitter..=jitter)
+                } else {
+                    1.0
+                };
+                let shift = if time_jitter > 0 {
+                    rng.random_range(-time_jitter..=time_jitter)
+                } else {
+                    0
+                };
+                let schedule = self.config.schedule(archetype, factor, shift);
+                let transport = &self.config.transport;
+                let modes = TransportMode::ALL
+                    .into_iter()
+                    .filter(|mode| match mode {
+                        TransportMode::Walk => true,
+                        TransportMode::Transit => false, // chosen per trip, nothing to own
+                        _ => rng.random_bool(transport.mode(*mode).ownership.clamp(0.0, 1.0)),
+                    })
+                    .collect::<Vec<_>>();
+                // cars start partly charged so charging demand is spread out
+                let battery = &transport.battery;
+                let charge = if battery.enabled() && modes.contains(&TransportMode::Drive) {
+                    battery.range * rng.random_range(battery.reserve..=1.0)
+                } else {
+                    battery.range
+                };
+                let id = self.agents.len() as AgentId;
+                let mut agent = Agent::new(id, archetype_index, home, work, park, schedule, modes);
+                agent.battery = charge;
+                // agents start out in the last activity of the day, at home until it ends
+                agent.dwell_until = agent
+                    .schedule
+                    .last()
+                    .map(|a| a.leave_at(self.current_time, self.current_time, &clock));
+                if let Some(cell) = self.city.get_cell_mut(&home) {
+                    cell.add_occupant();
+                }
+                self.agents.push(agent);
             }
-            self.agents.insert(agent.id.clone(), agent);
         }
+        Ok(())
     }
 
     pub async fn run(sim: Arc<Mutex<Simulation>>, running: Arc<AtomicBool>) {
+        // the clock continues after a stop, deadlines and trip times hold absolute ticks
         let tick_interval = {
-            let mut sim = sim.lock().await;
-            sim.current_time = 0;
+            let sim = sim.lock().await;
             Duration::from_secs_f64(1.0 / sim.config.tick_rate as f64)
         };
         let mut interval = time::interval(tick_interval);
@@ -75,60 +174,203 @@ impl Simulation {
             interval.tick().await;
             let mut sim = sim.lock().await;
             let tick_updates = sim.do_tick();
+            let sim = &mut *sim;
+            sim.trip_log.extend(&sim.completed_trips);
             _ = sim.tick_updates_broadcaster.send(tick_updates);
         }
     }
 
-    fn do_tick(&mut self) -> SimulationUpdate {
+    pub fn do_tick(&mut self) -> SimulationUpdate {
         self.current_time += 1;
         let now = self.current_time;
         let mut updates = SimulationUpdate {
             timestamp: now,
+            city_revision: self.city.revision(),
+            clock: ClockState::at(&self.config.clock(), now),
+            signals: self.city.signal_states(now),
             agents: vec![],
-            metrics: SimulationMetrics {
-                timestamp: now,
-                average_commute_time: 0.0,
-                average_distance: 0.0,
-                congestion_map: Default::default(),
-                most_congested_position: None,
-                max_congestion: 0,
-                energy_usage: 0.0,
-            },
+            vehicles: vec![],
+            metrics: SimulationMetrics::default(),
         };
 
         // calc stats
         let agent_metrics = self
             .agents
-            .values()
+            .iter()
             .map(AgentMetrics::from)
             .collect::<Vec<_>>();
 
-        for (_, agent) in self.agents.iter_mut() {
+        let routing = &self.config.routing;
+        let transport = &self.config.transport;
+        let costs = TransportMode::ALL.map(|mode| CongestionCost {
+            weight: routing.congestion_weight,
+            speed: transport.mode(mode).speed,
+        });
+        let planners = TransportMode::ALL.map(|mode| RoutePlanner {
+            router: self.router.as_ref(),
+            cost: &costs[mode as usize],
+            rules: transport
+                .mode(mode)
+                .passability
+                .as_ref()
+                .unwrap_or(&routing.passability),
+        });
+        // planning only reads the city, so agents plan in parallel
+        let city = &self.city;
+        let gridlock_timeout = self.config.gridlock_timeout;
+        self.agents.par_iter_mut().for_each(|agent| {
+            let planner = &planners[agent.mode as usize];
+            if agent.is_gridlocked(now, gridlock_timeout) {
+                // the queue may be part of a cycle of full cells, look for a way around
+                agent.blocked_since = Some(now);
+                agent.find_goal_path(city, planner);
+            } else {
+                agent.update_path(city, planner);
+            }
+            agent.check_range(city, planner, &transport.battery);
+            // spread reroute checks over ticks instead of doing them all at once
+            if routing.reroute_interval > 0
+                && (now + agent.id as i64) % routing.reroute_interval == 0
+            {
+                agent.reroute(city, planner, routing.reroute_threshold);
+            }
+        });
+
+        // agents queued the longest move first, so full cells are entered in FIFO order
+        let mut move_order = self
+            .agents
+            .iter()
+            .filter(|agent| !agent.path.is_empty())
+            .map(|agent| (agent.waiting_since.unwrap_or(WorldTime::MAX), agent.id))
+            .collect::<Vec<_>>();
+        move_order.sort_unstable();
+        for (_, id) in move_order {
+            let agent = &mut self.agents[id as usize];
+            if agent.stranded_since.is_some()
+                || agent.mode == TransportMode::Drive && transport.battery.is_empty(agent.battery)
+            {
+                // the car stops where it is until it was charged at the roadside
+                agent.stop_empty(now);
+                continue;
+            }
             let original_position = agent.position;
-            agent.find_goal_path(&self.city);
-            agent.move_along_path(now, &self.city);
+            let rules = planners[agent.mode as usize].rules;
+            let moved = agent.move_along_path(now, &self.city, rules);
+            agent.energy_used += moved as f64 * transport.mode(agent.mode).energy_per_cell;
+            if agent.mode == TransportMode::Drive && transport.battery.enabled() {
+                agent.battery = (agent.battery - moved as f64).max(0.0);
+            }
             if original_position != agent.position {
                 if let Some(cell) = self.city.get_cell_mut(&original_position) {
-                    cell.remove_occupant(&agent.id);
+                    cell.remove_occupant();
                 }
                 if let Some(cell) = self.city.get_cell_mut(&agent.position) {
-                    cell.add_occupant(&agent.id);
+                    cell.add_occupant();
                 }
             }
+        }
+
+        self.swap_head_to_head(now);
+        let transport = &self.config.transport;
+
+        self.transit.step(now, &mut self.agents, &mut self.city);
+        updates.vehicles = self.transit.vehicle_updates();
+        self.charging
+            .step(now, &mut self.agents, &self.city, &transport.battery);
+
+        let clock = self.config.clock();
+        self.completed_trips.clear();
+        updates.agents.reserve(self.agents.len());
+        for agent in &mut self.agents {
             updates.agents.push(AgentUpdate {
-                id: agent.id.clone(),
+                id: agent.id,
                 position: agent.position,
                 state: agent.state,
+                mode: agent.mode,
             });
             // update state after position has changed
-            agent.update_state(now);
+            agent.update_state(now, &clock, &self.city, transport, &self.transit);
+            if let Some(trip) = agent.trips.back().filter(|t| t.arrival == Some(now)) {
+                self.trip_times.add(trip);
+                self.completed_trips.push(AgentTrip {
+                    agent: agent.id,
+                    trip: trip.clone(),
+                });
+                // only the diary is capped, completed trips go to the log and exports
+                if agent.trips.len() > self.config.trip_history {
+                    agent.trips.pop_front();
+                }
+            }
         }
 
-        updates.metrics = calc_metrics(&self.city, &agent_metrics, self.current_time);
+        self.city
+            .observe_occupancy(self.config.routing.occupancy_smoothing);
+
+        updates.metrics = calc_metrics(
+            &self.city,
+            &agent_metrics,
+            &self.trip_times,
+            self.current_time,
+        );
+        updates.metrics.transit = self.transit.metrics(&self.agents);
+        updates.metrics.charging = self.charging.metrics(&self.agents, &transport.battery);
 
         updates
     }
 
+    /// agents queued in front of full cells that face each other trade places,
+    /// each leaves the cell the other enters so occupancy stays the same
+    fn swap_head_to_head(&mut self, now: WorldTime) {
+        // ordered so the same pairs swap in every run of a seed
+        let mut blocked: BTreeMap<Position, Vec<AgentId>> = BTreeMap::new();
+        for agent in self.agents.iter().filter(|a| a.blocked_since.is_some()) {
+            blocked.entry(agent.position).or_default().push(agent.id);
+        }
+        let mut swapped = HashSet::new();
+        for (position, ids) in &blocked {
+            for &a in ids {
+                if swapped.contains(&a) {
+                    continue;
+                }
+                let Some(&next) = self.agents[a as usize].path.front() else {
+                    continue;
+                };
+                let Some(&b) = blocked.get(&next).into_iter().flatten().find(|&&b| {
+                    self.agents[b as usize].path.front() == Some(position) && !swapped.contains(&b)
+                }) else {
+                    continue;
+                };
+                let legal = |id: AgentId| {
+                    let agent = &self.agents[id as usize];
+                    let next = agent.path.front()?;
+                    let direction = Direction::between(&agent.position, next)?;
+                    let cell = self.city.get_cell(next)?;
+                    let rules = self.config.passability(agent.mode);
+                    (rules.allows(&self.city, &agent.position, cell, &agent.target())
+                        && self
+                            .city
+                            .can_move(&agent.position, direction, agent.heading)
+                        && cell.is_green(now, direction))
+                    .then_some(direction)
+                };
+                let (Some(a_direction), Some(b_direction)) = (legal(a), legal(b)) else {
+                    continue;
+                };
+                for (id, direction) in [(a, a_direction), (b, b_direction)] {
+                    let agent = &mut self.agents[id as usize];
+                    agent.step(direction);
+                    agent.progress = 0.0;
+                    agent.energy_used += self.config.transport.mode(agent.mode).energy_per_cell;
+                    if agent.mode == TransportMode::Drive && self.config.transport.battery.enabled()
+                    {
+                        agent.battery = (agent.battery - 1.0).max(0.0);
+                    }
+                    swapped.insert(id);
+                }
+            }
+        }
+    }
+
     pub fn subscribe(&self) -> broadcast::Receiver<SimulationUpdate> {
         self.tick_updates_broadcaster.subscribe()
     }
@@ -137,15 +379,104 @@ impl Simulation {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimulationUpdate {
     pub timestamp: WorldTime,
+    pub city_revision: u64, // changes when the map was edited
+    pub clock: ClockState,
+    pub signals: Vec<SignalState>,
     pub agents: Vec<AgentUpdate>,
+    pub vehicles: Vec<VehicleUpdate>,
     pub metrics: SimulationMetrics,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentUpdate {
-    pub id: String,
+    pub id: AgentId,
     pub position: Position,
     pub state: AgentState,
+    pub mode: TransportMode, // of the current or last trip
+}
+
+/// cells of one type agents are assigned to
+struct Places {
+    cell_type: CellType,
+    cells: Vec<Position>,
+    /// cells with a free place and how many are left, `None` is unlimited
+    free: Vec<(Position, Option<usize>)>,
+}
+
+impl Places {
+    fn new(city: &CityGrid, cell_type: CellType) -> Self {
+        let cells = city.find_cells_of_type(cell_type);
+        let free = cells
+            .iter()
+            .filter_map(|position| Some((*position, city.get_cell(position)?.places)))
+            .filter(|(_, places)| *places != Some(0))
+            .collect();
+        Self {
+            cell_type,
+            cells,
+            free,
+        }
+    }
+
+    /// random cell with a free place, `None` once every place is taken
+    fn take(&mut self, rng: &mut StdRng) -> Option<Position> {
+        if self.free.is_empty() {
+            return None;
+        }
+        let i = rng.random_range(0..self.free.len());
+        let (position, free) = &mut self.free[i];
+        let position = *position;
+        if let Some(n) = free {
+            *n -= 1;
+            if *n == 0 {
+                self.free.swap_remove(i);
+            }
+        }
+        Some(position)
+    }
+
+    /// the shortage once `take` fails, `demand` as in `SimulationConfig::place_demand`
+    fn shortage(&self, city: &CityGrid, demand: &[(CellType, usize)]) -> Issue {
+        Issue::NotEnoughPlaces {
+            cell_type: self.cell_type,
+            places: self
+                .cells
+                .iter()
+                .filter_map(|position| city.get_cell(position)?.places)
+                .sum(),
+            agents: demand
+                .iter()
+                .find(|(t, _)| *t == self.cell_type)
+                .map_or(0, |(_, n)| *n),
+        }
+    }
 }
 
 pub type WorldTime = i64;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::city::config::CityConfig;
+
+    /// every update of `ticks` ticks on the built-in map, serialized
+    fn updates(seed: u64, ticks: usize) -> Vec<String> {
+        let config = SimulationConfig {
+            num_agents: 30,
+            seed,
+            ..SimulationConfig::default()
+        };
+        let city = CityConfig::default().to_city_grid().unwrap();
+        let mut sim = Simulation::new(city, config);
+        sim.initialize().unwrap();
+        (0..ticks)
+            .map(|_| serde_json::to_string(&sim.do_tick()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_updates() {
+        // agents leave home in the morning wave, past tick 350
+        assert_eq!(updates(7, 600), updates(7, 600));
+    }
+}
@@ -0,0 +1,391 @@
+use crate::agent::agent::{Agent, AgentId};
+use crate::city::cell::Position;
+use crate::city::grid::CityGrid;
+use crate::routing::passability::PassabilityRules;
+use crate::simulation::simulation::WorldTime;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+
+/// one ride of a transit trip, stops are indexes into `Line::sequence`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Leg {
+    pub line: usize,
+    pub from: usize,
+    pub to: usize,
+    pub board: Position,
+    pub alight: Position,
+}
+
+/// a transit line resolved to a timetable
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub name: String,
+    /// stops in service order, repeated after the last
+    pub sequence: Vec<Position>,
+    pub headway: WorldTime,
+    pub capacity: usize,
+    /// vehicle position and the stop it serves, if any, per tick of a round trip
+    timetable: Vec<(Position, Option<usize>)>,
+    /// tick of the round trip each stop of `sequence` is reached at
+    arrivals: Vec<WorldTime>,
+}
+
+impl Line {
+    fn cycle(&self) -> WorldTime {
+        self.timetable.len() as WorldTime
+    }
+
+    /// whether vehicles of the line drive over `position`
+    pub fn passes(&self, position: &Position) -> bool {
+        self.timetable.iter().any(|(p, _)| p == position)
+    }
+
+    /// ticks from reaching stop `i` to reaching the next one
+    fn ride_time(&self, i: usize) -> WorldTime {
+        let next = (i + 1) % self.sequence.len();
+        (self.arrivals[next] - self.arrivals[i]).rem_euclid(self.cycle())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Vehicle {
+    pub line: usize,
+    pub offset: WorldTime, // ticks behind the first vehicle of the line
+    pub position: Position,
+    pub passengers: Vec<AgentId>,
+    /// passengers carried past their stop because it was full, they get off at the next one with room
+    pub overdue: Vec<AgentId>,
+}
+
+/// transit ridership so far
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransitMetrics {
+    pub riders: usize,  // agents on board
+    pub waiting: usize, // agents waiting at a stop
+    pub boardings: usize,
+    pub transfers: usize,       // boardings after alighting on the same trip
+    pub average_wait_time: f64, // ticks at the stop per boarding
+    pub boardings_by_line: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VehicleUpdate {
+    pub line: String,
+    pub position: Position,
+    pub passengers: usize,
+}
+
+/// transit lines of the city and their vehicles
+///
+/// vehicles keep to the timetable, they are not held up by signals or traffic
+/// and do not take up road capacity
+#[derive(Debug, Clone, Default)]
+pub struct Transit {
+    pub lines: Vec<Line>,
+    pub vehicles: Vec<Vehicle>,
+    /// indexes into the lines' sequences per stop position
+    stops: HashMap<Position, Vec<(usize, usize)>>,
+    boardings: Vec<usize>, // per line
+    transfers: usize,
+    total_wait_time: WorldTime,
+}
+
+impl Transit {
+    /// timetables for the lines of `city`, lines with unconnected stops are left out
+    pub fn new(city: &CityGrid, rules: &PassabilityRules) -> Self {
+        let mut transit = Transit::default();
+        for config in &city.lines {
+            if config.stops.len() < 2
+                || config.headway <= 0
+                || config.capacity == 0
+                || config.speed <= 0.0
+            {
+                log::warn!("line {:?}: invalid, not running", config.name);
+                continue;
+            }
+            let segments = match config.route(city, rules) {
+                Ok(segments) => segments,
+                Err((from, to)) => {
+                    log::warn!(
+                        "line {:?}: no road from ({}, {}) to ({}, {}), not running",
+                        config.name,
+                        from.x,
+                        from.y,
+                        to.x,
+                        to.y
+                    );
+                    continue;
+                }
+            };
+            let sequence = config.sequence();
+            let mut timetable = Vec::new();
+            let mut arrivals = Vec::with_capacity(sequence.len());
+            for (i, (stop, segment)) in sequence.iter().zip(&segments).enumerate() {
+                arrivals.push(timetable.len() as WorldTime);
+                for _ in 0..config.dwell.max(1) {
+                    timetable.push((*stop, Some(i)));
+                }
+                // the tick the next stop is reached starts its dwell
+                let ticks = (segment.len() as f64 / config.speed).ceil() as usize;
+                for tick in 1..ticks {
+                    let cells = ((tick as f64 * config.speed) as usize).min(segment.len());
+                    let position = cells.checked_sub(1).map_or(*stop, |c| segment[c]);
+                    timetable.push((position, None));
+                }
+            }
+
+            let index = transit.lines.len();
+            for (i, stop) in sequence.iter().enumerate() {
+                transit.stops.entry(*stop).or_default().push((index, i));
+            }
+            let line = Line {
+                name: config.name.clone(),
+                sequence,
+                headway: config.headway,
+                capacity: config.capacity,
+                timetable,
+                arrivals,
+            };
+            let fleet = (line.cycle() + line.headway - 1) / line.headway;
+            transit.vehicles.extend((0..fleet).map(|k| Vehicle {
+                line: index,
+                offset: k * line.headway,
+                position: line.sequence[0],
+                passengers: Vec::new(),
+                overdue: Vec::new(),
+            }));
+            transit.lines.push(line);
+        }
+        transit.boardings = vec![0; transit.lines.len()];
+        transit
+    }
+
+    /// quickest itinerary from `from` to `to` with its estimated duration in ticks,
+    /// walking at most `max_walk` cells to the first and from the last stop
+    ///
+    /// waits are estimated as half a headway, transfers happen at shared stops
+    pub fn plan(
+        &self,
+        from: &Position,
+        to: &Position,
+        max_walk: usize,
+        walk_speed: f64,
+    ) -> Option<(Vec<Leg>, WorldTime)> {
+        let walk = |distance: usize| (distance as f64 / walk_speed).ceil() as WorldTime;
+        let offsets = self
+            .lines
+            .iter()
+            .scan(0, |n, line| {
+                let offset = *n;
+                *n += line.sequence.len();
+                Some(offset)
+            })
+            .collect::<Vec<_>>();
+        let count = self
+            .lines
+            .iter()
+            .map(|line| line.sequence.len())
+            .sum::<usize>();
+        // each (line, stop) is reached either waiting at the stop or on board
+        let state = |line: usize, stop: usize, on_board: bool| {
+            (offsets[line] + stop) * 2 + on_board as usize
+        };
+
+        // cost to reach each state, and the state it was reached from
+        let mut costs = vec![WorldTime::MAX; count * 2];
+        let mut parents: Vec<Option<(usize, usize, bool)>> = vec![None; count * 2]; // line, stop, on board
+        let mut open = BinaryHeap::new();
+        for (line_index, line) in self.lines.iter().enumerate() {
+            for (stop, position) in line.sequence.iter().enumerate() {
+                let distance = from.distance(position);
+                if distance <= max_walk {
+                    let cost = walk(distance) + line.headway / 2;
+                    if cost < costs[state(line_index, stop, false)] {
+                        costs[state(line_index, stop, false)] = cost;
+                        open.push(Reverse((cost, line_index, stop, false)));
+                    }
+                }
+            }
+        }
+
+        let mut best: Option<(WorldTime, usize, usize)> = None;
+        while let Some(Reverse((cost, line_index, stop, on_board))) = open.pop() {
+            if cost > costs[state(line_index, stop, on_board)] {
+                continue;
+            }
+            let line = &self.lines[line_index];
+            let position = line.sequence[stop];
+            let distance = position.distance(to);
+            if on_board && distance <= max_walk {
+                let total = cost + walk(distance);
+                if best.is_none_or(|(b, _, _)| total < b) {
+                    best = Some((total, line_index, stop));
+                }
+            }
+
+            // ride on to the next stop, waiting agents board first
+            let next = (stop + 1) % line.sequence.len();
+            let mut moves = vec![(line_index, next, line.ride_time(stop), true)];
+            // transfer only after a ride, boarding another line right away is a start of its own
+            if on_board {
+                for &(other, other_stop) in self.stops.get(&position).into_iter().flatten() {
+                    if other != line_index {
+                        moves.push((other, other_stop, self.lines[other].headway / 2, false));
+                    }
+                }
+            }
+            for (l, s, step, board) in moves {
+                let cost = cost + step;
+                if cost < costs[state(l, s, board)] {
+                    costs[state(l, s, board)] = cost;
+                    parents[state(l, s, board)] = Some((line_index, stop, on_board));
+                    open.push(Reverse((cost, l, s, board)));
+                }
+            }
+        }
+
+        // walk back from the last stop, every waiting state starts a leg
+        let (total, mut line, mut stop) = best?;
+        let mut on_board = true;
+        let mut legs = Vec::new();
+        let mut alight = stop;
+        loop {
+            let parent = parents[state(line, stop, on_board)];
+            if on_board {
+                (line, stop, on_board) = parent?;
+                continue;
+            }
+            legs.push(self.leg(line, stop, alight));
+            let Some((previous_line, previous_stop, _)) = parent else {
+                break;
+            };
+            alight = previous_stop;
+            (line, stop, on_board) = (previous_line, previous_stop, true);
+        }
+        legs.reverse();
+        Some((legs, total))
+    }
+
+    fn leg(&self, line: usize, from: usize, to: usize) -> Leg {
+        let sequence = &self.lines[line].sequence;
+        Leg {
+            line,
+            from,
+            to,
+            board: sequence[from],
+            alight: sequence[to],
+        }
+    }
+
+    /// move vehicles along their timetable, let passengers off at their stop or the next
+    /// one with room and agents waiting for the line on, longest waiting first
+    pub fn step(&mut self, now: WorldTime, agents: &mut [Agent], city: &mut CityGrid) {
+        if self.vehicles.is_empty() {
+            return;
+        }
+        let mut waiting: HashMap<(usize, usize), Vec<(WorldTime, AgentId)>> = HashMap::new();
+        for agent in agents.iter_mut().filter(|a| a.riding.is_none()) {
+            let Some(leg) = agent.itinerary.front() else {
+                continue;
+            };
+            if agent.position == leg.board {
+                let since = *agent.transit_wait_since.get_or_insert(now);
+                waiting
+                    .entry((leg.line, leg.from))
+                    .or_default()
+                    .push((since, agent.id));
+            }
+        }
+        for queue in waiting.values_mut() {
+            queue.sort_unstable_by_key(|entry| Reverse(*entry)); // longest waiting last
+        }
+
+        for (index, vehicle) in self.vehicles.iter_mut().enumerate() {
+            let line = &self.lines[vehicle.line];
+            let phase = (now - vehicle.offset).rem_euclid(line.cycle()) as usize;
+            let (position, stop) = line.timetable[phase];
+            vehicle.position = position;
+            for id in &vehicle.passengers {
+                agents[*id as usize].ride_to(position);
+            }
+            let Some(stop) = stop else {
+                continue;
+            };
+
+            let overdue = &mut vehicle.overdue;
+            vehicle.passengers.retain(|id| {
+                let agent = &mut agents[*id as usize];
+                let alights = overdue.contains(id)
+                    || agent
+                        .itinerary
+                        .front()
+                        .is_none_or(|leg| leg.line == vehicle.line && leg.to == stop);
+                if !alights {
+                    return true;
+                }
+                let Some(cell) = city.get_cell_mut(&position).filter(|c| !c.is_full()) else {
+                    // no room at the stop, ride on
+                    if !overdue.contains(id) {
+                        overdue.push(*id);
+                    }
+                    return true;
+                };
+                cell.add_occupant();
+                overdue.retain(|other| other != id);
+                if agent.alight() {
+                    self.transfers += 1;
+                }
+                false
+            });
+
+            let Some(queue) = waiting.get_mut(&(vehicle.line, stop)) else {
+                continue;
+            };
+            while vehicle.passengers.len() < line.capacity {
+                let Some((since, id)) = queue.pop() else {
+                    break;
+                };
+                let agent = &mut agents[id as usize];
+                if let Some(cell) = city.get_cell_mut(&agent.position) {
+                    cell.remove_occupant();
+                }
+                agent.board(index, now - since);
+                self.total_wait_time += now - since;
+                self.boardings[vehicle.line] += 1;
+                vehicle.passengers.push(id);
+            }
+        }
+    }
+
+    pub fn metrics(&self, agents: &[Agent]) -> TransitMetrics {
+        let boardings = self.boardings.iter().sum::<usize>();
+        TransitMetrics {
+            riders: self.vehicles.iter().map(|v| v.passengers.len()).sum(),
+            waiting: agents
+                .iter()
+                .filter(|a| a.transit_wait_since.is_some())
+                .count(),
+            boardings,
+            transfers: self.transfers,
+            average_wait_time: self.total_wait_time as f64 / boardings.max(1) as f64,
+            boardings_by_line: self
+                .lines
+                .iter()
+                .zip(&self.boardings)
+                .map(|(line, n)| (line.name.clone(), *n))
+                .collect(),
+        }
+    }
+
+    pub fn vehicle_updates(&self) -> Vec<VehicleUpdate> {
+        self.vehicles
+            .iter()
+            .map(|v| VehicleUpdate {
+                line: self.lines[v.line].name.clone(),
+                position: v.position,
+                passengers: v.passengers.len(),
+            })
+            .collect()
+    }
+}
@@ -1,17 +1,26 @@
 use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
-use axum::extract::State;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use axum::routing::get;
+use axum::routing::{get, post};
 use axum::{Json, Router};
 use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
 use serde_json::json;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::{atomic, Arc};
 use tokio::sync::Mutex;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::ServeDir;
 
-use crate::simulation::simulation::Simulation;
+use crate::agent::agent::AgentId;
+use crate::agent::trip::TripPage;
+use crate::city::cell::Position;
+use crate::city::config::CellConfig;
+use crate::routing::analysis::{self, NetworkAnalysis, TripDistance};
+use crate::simulation::edit::EditReport;
+use crate::simulation::simulation::{Simulation, WorldTime};
 
 #[derive(Clone)]
 pub struct AppState {
@@ -22,6 +31,7 @@ pub struct AppState {
 pub async fn start_server(
     simulation: Arc<Mutex<Simulation>>,
     addr: SocketAddr,
+    static_dir: PathBuf,
 ) -> Result<(), anyhow::Error> {
     let state = AppState {
         simulation,
@@ -36,9 +46,15 @@ pub async fn start_server(
     let app = Router::new()
         .route("/ws", get(ws_handler))
         .route("/api/city", get(get_city))
+        .route("/api/city/cells", post(edit_cells))
+        .route("/api/city/analysis", get(get_analysis))
+        .route("/api/city/analysis/trips", get(get_trip_distances))
+        .route("/api/city/analysis/pair", get(get_pair_distance))
+        .route("/api/agents/{id}/trips", get(get_agent_trips))
+        .route("/api/trips", get(get_trips))
         .route("/api/start", get(start_simulation))
         .route("/api/stop", get(stop_simulation))
-        .fallback_service(ServeDir::new("frontend/dist"))
+        .fallback_service(ServeDir::new(static_dir))
         .layer(cors)
         .with_state(state);
 
@@ -48,7 +64,7 @@ pub async fn start_server(
     Ok(())
 }
 
-async fn ws_handler<'a>(ws: WebSocketUpgrade, State(state): State<AppState>) -> impl IntoResponse {
+async fn ws_handler(ws: WebSocketUpgrade, State(state): State<AppState>) -> impl IntoResponse {
     ws.on_upgrade(|socket| handle_socket(socket, state))
 }
 
@@ -56,7 +72,7 @@ async fn ws_handler<'a>(ws: WebSocketUpgrade, State(state): State<AppState>) ->
 /// send updates through websocket
 async fn handle_socket(socket: WebSocket, state: AppState) {
     let (mut sender, mut receiver) = socket.split();
-    tokio::spawn(async move { while let Some(message) = receiver.next().await {} });
+    tokio::spawn(async move { while receiver.next().await.is_some() {} });
 
     // subscribe to simulation updates
     let mut rx = {
@@ -88,17 +104,139 @@ async fn get_city(State(state): State<AppState>) -> Json<serde_json::Value> {
     Json(json!({
         "width": city.width,
         "height": city.height,
-        "cells": city.cells,
+        "revision": city.revision(),
+        "cells": city.rows().collect::<Vec<_>>(),
+        "lines": city.lines,
     }))
 }
 
+/// connected road networks and house to office/park trip distances
+async fn get_analysis(State(state): State<AppState>) -> Json<NetworkAnalysis> {
+    let (city, rules) = {
+        let sim = state.simulation.lock().await;
+        (sim.city.clone(), sim.config.routing.passability.clone())
+    };
+    Json(analysis::analyze(&city, &rules))
+}
+
+/// shortest legal distances from the building at `x`, `y` to every office and park
+async fn get_trip_distances(
+    State(state): State<AppState>,
+    Query(from): Query<Position>,
+) -> Result<Json<Vec<TripDistance>>, (StatusCode, Json<serde_json::Value>)> {
+    let (city, rules) = {
+        let sim = state.simulation.lock().await;
+        (sim.city.clone(), sim.config.routing.passability.clone())
+    };
+    if city.get_cell(&from).is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": format!("({}, {}) is outside the city", from.x, from.y) })),
+        ));
+    }
+    Ok(Json(analysis::trip_distances(&city, &rules, from)))
+}
+
+#[derive(Debug, Deserialize)]
+struct PairQuery {
+    from_x: usize,
+    from_y: usize,
+    to_x: usize,
+    to_y: usize,
+}
+
+/// shortest legal distance between two buildings, `null` when unreachable
+async fn get_pair_distance(
+    State(state): State<AppState>,
+    Query(query): Query<PairQuery>,
+) -> Result<Json<TripDistance>, (StatusCode, Json<serde_json::Value>)> {
+    let (city, rules) = {
+        let sim = state.simulation.lock().await;
+        (sim.city.clone(), sim.config.routing.passability.clone())
+    };
+    let from = Position::new(query.from_x, query.from_y);
+    let to = Position::new(query.to_x, query.to_y);
+    for position in [from, to] {
+        if city.get_cell(&position).is_none() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": format!("({}, {}) is outside the city", position.x, position.y)
+                })),
+            ));
+        }
+    }
+    Ok(Json(analysis::pair_distance(&city, &rules, from, to)))
+}
+
+/// replace cells while the simulation runs, e.g. build a road
+async fn edit_cells(
+    State(state): State<AppState>,
+    Json(edits): Json<Vec<CellConfig>>,
+) -> Result<Json<EditReport>, (StatusCode, Json<serde_json::Value>)> {
+    let mut sim = state.simulation.lock().await;
+    sim.edit_cells(&edits)
+        .map(Json)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(json!({ "error": error }))))
+}
+
+/// the agent's recorded trips, oldest first, and the one under way
+async fn get_agent_trips(
+    State(state): State<AppState>,
+    Path(id): Path<AgentId>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let sim = state.simulation.lock().await;
+    let agent = sim.agents.get(id as usize).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": format!("no agent {}", id) })),
+        )
+    })?;
+    Ok(Json(json!({
+        "agent": agent.id,
+        "current": agent.trip,
+        "trips": agent.trips,
+    })))
+}
+
+/// most trips listed per request
+const MAX_TRIPS_PAGE: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+struct TripsQuery {
+    /// only trips that arrived after this tick
+    since: Option<WorldTime>,
+    /// continue from the `next` cursor of the previous page
+    cursor: Option<u64>,
+    limit: Option<usize>,
+}
+
+/// logged trips of every agent, a page at a time, for bulk export
+async fn get_trips(
+    State(state): State<AppState>,
+    Query(query): Query<TripsQuery>,
+) -> Json<TripPage> {
+    let limit = query.limit.unwrap_or(MAX_TRIPS_PAGE).min(MAX_TRIPS_PAGE);
+    let sim = state.simulation.lock().await;
+    Json(sim.trip_log.page(
+        query.cursor.unwrap_or(0),
+        query.since.unwrap_or(WorldTime::MIN),
+        limit,
+    ))
+}
+
 async fn start_simulation(State(state): State<AppState>) -> Json<serde_json::Value> {
-    if let Err(_) = state.running.clone().compare_exchange(
-        false,
-        true,
-        atomic::Ordering::SeqCst,
-        atomic::Ordering::SeqCst,
-    ) {
+    if state
+        .running
+        .clone()
+        .compare_exchange(
+            false,
+            true,
+            atomic::Ordering::SeqCst,
+            atomic::Ordering::SeqCst,
+        )
+        .is_err()
+    {
         return Json(json!({ "status": "already_running" }));
     }
     let new_running = Arc::clone(&state.running);